@@ -0,0 +1,150 @@
+//! Authentication of requests sent to the cruncher client API
+//!
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::fmt;
+
+pub const TIMESTAMP_HEADER: &str = "X-Cruncher-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-Cruncher-Signature";
+
+/// Credentials attached to every client API request.
+#[derive(Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ClientApiAuth {
+    /// `Authorization: Bearer <token>` header
+    Bearer { token: String },
+    /// HMAC-SHA256 of timestamp, method, path and body, keyed with shared `secret`
+    HmacSha256 { secret: String },
+}
+
+impl ClientApiAuth {
+    /// Credentials from env take precedence over the ones from runtime config.
+    pub fn resolve(
+        token: Option<String>,
+        hmac_secret: Option<String>,
+        config: Option<ClientApiAuth>,
+    ) -> anyhow::Result<Option<Self>> {
+        let auth = match (token, hmac_secret) {
+            (Some(_), Some(_)) => {
+                anyhow::bail!("Client API token and HMAC secret cannot be used together")
+            }
+            (Some(token), None) => Some(ClientApiAuth::Bearer { token }),
+            (None, Some(secret)) => Some(ClientApiAuth::HmacSha256 { secret }),
+            (None, None) => config,
+        };
+        if let Some(auth) = &auth {
+            auth.headers()?;
+        }
+        Ok(auth)
+    }
+
+    /// Headers sent with every request. Fails on token which is not a valid header value.
+    pub fn headers(&self) -> anyhow::Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        match self {
+            ClientApiAuth::Bearer { token } => {
+                let mut value = HeaderValue::from_str(&format!("Bearer {token}"))
+                    .ok()
+                    .filter(|_| !token.is_empty())
+                    .ok_or_else(|| anyhow::anyhow!("Invalid client API token"))?;
+                value.set_sensitive(true);
+                headers.insert(AUTHORIZATION, value);
+            }
+            ClientApiAuth::HmacSha256 { secret } => {
+                if secret.is_empty() {
+                    anyhow::bail!("Empty client API HMAC secret");
+                }
+            }
+        }
+        Ok(headers)
+    }
+
+    /// Signs request with HMAC. Bearer token is sent with [`ClientApiAuth::headers`].
+    pub fn sign(&self, request: &mut reqwest::Request) {
+        match self {
+            ClientApiAuth::Bearer { .. } => (),
+            ClientApiAuth::HmacSha256 { secret } => {
+                let timestamp = Utc::now().timestamp();
+                let body = request
+                    .body()
+                    .and_then(|body| body.as_bytes())
+                    .unwrap_or_default();
+                let url = request.url();
+                let path = match url.query() {
+                    Some(query) => format!("{}?{query}", url.path()),
+                    None => url.path().to_string(),
+                };
+                let signature = signature(
+                    secret,
+                    &timestamp.to_string(),
+                    request.method().as_str(),
+                    &path,
+                    body,
+                );
+
+                let headers = request.headers_mut();
+                headers.insert(TIMESTAMP_HEADER, HeaderValue::from(timestamp));
+                headers.insert(
+                    SIGNATURE_HEADER,
+                    HeaderValue::from_str(&signature).expect("hex is a valid header value"),
+                );
+            }
+        }
+    }
+}
+
+/// Hex encoded HMAC-SHA256 of `<timestamp>\n<method>\n<path>\n<body>`.
+pub fn signature(secret: &str, timestamp: &str, method: &str, path: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(format!("{timestamp}\n{method}\n{path}\n").as_bytes());
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Secrets are never logged.
+impl fmt::Debug for ClientApiAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientApiAuth::Bearer { .. } => write!(f, "Bearer"),
+            ClientApiAuth::HmacSha256 { .. } => write!(f, "HmacSha256"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_rejects_invalid_credentials() {
+        let bearer = |token: &str| {
+            Some(ClientApiAuth::Bearer {
+                token: token.to_string(),
+            })
+        };
+        assert_eq!(
+            ClientApiAuth::resolve(Some("token".to_string()), None, None).unwrap(),
+            bearer("token")
+        );
+        assert_eq!(
+            ClientApiAuth::resolve(None, None, bearer("config")).unwrap(),
+            bearer("config")
+        );
+
+        assert!(ClientApiAuth::resolve(Some("line\nbreak".to_string()), None, None).is_err());
+        assert!(ClientApiAuth::resolve(Some(String::new()), None, None).is_err());
+        assert!(ClientApiAuth::resolve(None, None, bearer("tab\tin\u{7f}")).is_err());
+        assert!(ClientApiAuth::resolve(None, Some(String::new()), None).is_err());
+        assert!(ClientApiAuth::resolve(
+            Some("token".to_string()),
+            Some("secret".to_string()),
+            None
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,216 @@
+//! Storage of batch results with bounded retention
+//!
+
+use chrono::Utc;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use ya_client_model::activity::{CommandResult, ExeScriptCommandResult};
+use ya_core_model::activity::RpcMessageError;
+
+struct Batch {
+    results: Vec<ExeScriptCommandResult>,
+    finished_at: Option<Instant>,
+}
+
+/// Results of executed batches.
+/// Finished batches are dropped after `ttl`, and the oldest finished batches are dropped
+/// when there are more than `max_batches` of them. Running batches are never dropped.
+pub struct BatchStore {
+    batches: HashMap<String, Batch>,
+    /// Batch ids in order of creation
+    order: VecDeque<String>,
+    /// Recently dropped batch ids, used to report eviction
+    evicted: VecDeque<String>,
+    max_batches: usize,
+    ttl: Duration,
+}
+
+impl BatchStore {
+    pub fn new(max_batches: usize, ttl: Duration) -> Self {
+        Self {
+            batches: Default::default(),
+            order: Default::default(),
+            evicted: Default::default(),
+            max_batches,
+            ttl,
+        }
+    }
+
+    pub fn start(&mut self, batch_id: &str) {
+        self.prune(Instant::now());
+        let batch = Batch {
+            results: Vec::new(),
+            finished_at: None,
+        };
+        if self.batches.insert(batch_id.to_string(), batch).is_none() {
+            self.order.push_back(batch_id.to_string());
+        }
+    }
+
+    pub fn push(&mut self, batch_id: &str, result: ExeScriptCommandResult) {
+        self.push_at(batch_id, result, Instant::now())
+    }
+
+    fn push_at(&mut self, batch_id: &str, result: ExeScriptCommandResult, now: Instant) {
+        let Some(batch) = self.batches.get_mut(batch_id) else {
+            log::warn!("Result of unknown batch {batch_id} dropped");
+            return;
+        };
+        if result.is_batch_finished {
+            batch.finished_at = Some(now);
+        }
+        batch.results.push(result);
+    }
+
+    pub fn get(&mut self, batch_id: &str) -> Result<Vec<ExeScriptCommandResult>, RpcMessageError> {
+        self.prune(Instant::now());
+        match self.batches.get(batch_id) {
+            Some(batch) => Ok(batch.results.clone()),
+            None if self.evicted.iter().any(|id| id == batch_id) => Err(RpcMessageError::NotFound(
+                format!("Batch id={batch_id} evicted"),
+            )),
+            None => Err(RpcMessageError::NotFound(format!("Batch id={batch_id}"))),
+        }
+    }
+
+    pub fn contains(&self, batch_id: &str) -> bool {
+        self.batches.contains_key(batch_id)
+    }
+
+    fn prune(&mut self, now: Instant) {
+        let ttl = self.ttl;
+        let expired = |batch: &Batch| {
+            batch
+                .finished_at
+                .map(|finished_at| now.saturating_duration_since(finished_at) >= ttl)
+                .unwrap_or(false)
+        };
+
+        let mut finished = self
+            .batches
+            .values()
+            .filter(|batch| batch.finished_at.is_some())
+            .count();
+        let order = std::mem::take(&mut self.order);
+        for batch_id in order {
+            let batch = &self.batches[&batch_id];
+            let evict =
+                expired(batch) || (batch.finished_at.is_some() && finished > self.max_batches);
+            if evict {
+                finished -= 1;
+                self.batches.remove(&batch_id);
+                self.evicted.push_back(batch_id);
+            } else {
+                self.order.push_back(batch_id);
+            }
+        }
+
+        while self.evicted.len() > self.max_batches.max(1) {
+            self.evicted.pop_front();
+        }
+    }
+}
+
+/// Whether batch of `len` commands is finished with command at `index`.
+/// Failed command finishes the batch, unless the batch continues on errors.
+pub fn is_batch_finished(index: usize, len: usize, failed: bool, continue_on_error: bool) -> bool {
+    index + 1 >= len || (failed && !continue_on_error)
+}
+
+/// Result marking batch without commands as finished.
+pub fn empty_batch_result() -> ExeScriptCommandResult {
+    ExeScriptCommandResult {
+        index: 0,
+        result: CommandResult::Ok,
+        stdout: None,
+        stderr: None,
+        message: Some("Empty batch".to_string()),
+        is_batch_finished: true,
+        event_date: Utc::now(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(index: u32, is_batch_finished: bool) -> ExeScriptCommandResult {
+        ExeScriptCommandResult {
+            index,
+            result: CommandResult::Ok,
+            stdout: None,
+            stderr: None,
+            message: None,
+            is_batch_finished,
+            event_date: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_evicts_oldest_finished_batches() {
+        let mut store = BatchStore::new(2, Duration::from_secs(3600));
+        store.start("running");
+        store.push("running", result(0, false));
+        for batch_id in ["first", "second", "third"] {
+            store.start(batch_id);
+            store.push(batch_id, result(0, true));
+        }
+        store.start("fourth");
+
+        assert!(matches!(
+            store.get("first"),
+            Err(RpcMessageError::NotFound(message)) if message.contains("evicted")
+        ));
+        assert_eq!(store.get("second").unwrap().len(), 1);
+        assert_eq!(store.get("third").unwrap().len(), 1);
+        assert_eq!(store.get("running").unwrap().len(), 1);
+        assert!(store.get("fourth").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_evicts_finished_batches_after_ttl() {
+        let ttl = Duration::from_secs(60);
+        let mut store = BatchStore::new(10, ttl);
+        let now = Instant::now();
+        store.start("finished");
+        store.push_at("finished", result(0, false), now);
+        store.push_at("finished", result(1, true), now);
+        store.start("running");
+        store.push_at("running", result(0, false), now);
+
+        store.prune(now + ttl / 2);
+        assert_eq!(store.get("finished").unwrap().len(), 2);
+
+        store.prune(now + ttl);
+        assert!(!store.contains("finished"));
+        assert!(store.contains("running"));
+        assert!(matches!(
+            store.get("unknown"),
+            Err(RpcMessageError::NotFound(message)) if !message.contains("evicted")
+        ));
+    }
+
+    #[test]
+    fn test_is_batch_finished() {
+        assert!(!is_batch_finished(0, 3, false, false));
+        assert!(!is_batch_finished(1, 3, false, false));
+        assert!(is_batch_finished(2, 3, false, false));
+        assert!(is_batch_finished(0, 1, false, false));
+
+        assert!(is_batch_finished(0, 3, true, false));
+        assert!(!is_batch_finished(0, 3, true, true));
+        assert!(is_batch_finished(2, 3, true, true));
+    }
+
+    #[test]
+    fn test_empty_batch_finished() {
+        let mut store = BatchStore::new(10, Duration::from_secs(60));
+        store.start("empty");
+        store.push("empty", empty_batch_result());
+
+        let results = store.get("empty").unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].index, 0);
+        assert!(results[0].is_batch_finished);
+    }
+}
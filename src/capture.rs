@@ -0,0 +1,198 @@
+//! Handling of `capture` field of `ExeScriptCommand::Run`
+//!
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use ya_client_model::activity::{Capture, CaptureFormat, CaptureMode, CapturePart, CommandOutput};
+
+const CAPTURE_DIR: &str = "capture";
+/// `Run` argument requesting full outputs to be stored with [`capture_to_file`]
+pub const CAPTURE_FILE_ARG: &str = "--capture-file";
+
+/// Command output captured with requested mode.
+#[derive(Debug, Clone)]
+pub enum Captured {
+    /// Returned in command result
+    AtEnd(CommandOutput),
+    /// Emitted as runtime event
+    Stream(CommandOutput),
+}
+
+impl Captured {
+    /// Output returned in command result. Streamed output is not repeated there.
+    pub fn into_result(self) -> Option<CommandOutput> {
+        match self {
+            Captured::AtEnd(output) => Some(output),
+            Captured::Stream(_) => None,
+        }
+    }
+}
+
+/// Applies requested `capture` to command outputs.
+/// Without `capture` both outputs are returned as strings at end, unmodified.
+pub fn capture_output(
+    capture: &Option<Capture>,
+    stdout: &str,
+    stderr: &str,
+) -> (Option<Captured>, Option<Captured>) {
+    match capture {
+        None => (
+            Some(Captured::AtEnd(CommandOutput::Str(stdout.to_string()))),
+            Some(Captured::AtEnd(CommandOutput::Str(stderr.to_string()))),
+        ),
+        Some(capture) => (
+            capture
+                .stdout
+                .as_ref()
+                .map(|mode| capture_with_mode(mode, stdout)),
+            capture
+                .stderr
+                .as_ref()
+                .map(|mode| capture_with_mode(mode, stderr)),
+        ),
+    }
+}
+
+fn capture_with_mode(mode: &CaptureMode, output: &str) -> Captured {
+    match mode {
+        CaptureMode::AtEnd { part, format } => {
+            Captured::AtEnd(format_output(format, capture_part(part, output.as_bytes())))
+        }
+        CaptureMode::Stream { limit, format } => Captured::Stream(format_output(
+            format,
+            capture_part(&limit.map(CapturePart::Head), output.as_bytes()),
+        )),
+    }
+}
+
+fn format_output(format: &Option<CaptureFormat>, bytes: Vec<u8>) -> CommandOutput {
+    match format {
+        Some(CaptureFormat::Bin) => CommandOutput::Bin(bytes),
+        Some(CaptureFormat::Str) | None => {
+            CommandOutput::Str(String::from_utf8_lossy(&bytes).into_owned())
+        }
+    }
+}
+
+fn capture_part(part: &Option<CapturePart>, output: &[u8]) -> Vec<u8> {
+    match part {
+        None => output.to_vec(),
+        Some(CapturePart::Head(limit)) => output[..output.len().min(*limit)].to_vec(),
+        Some(CapturePart::Tail(limit)) => output[output.len().saturating_sub(*limit)..].to_vec(),
+        Some(CapturePart::HeadTail(limit)) => {
+            if output.len() <= *limit {
+                return output.to_vec();
+            }
+            let head = limit / 2;
+            let tail = limit - head;
+            [&output[..head], &output[output.len() - tail..]].concat()
+        }
+    }
+}
+
+/// Stores full, untruncated command outputs in `work_dir`, so they can be downloaded with `Transfer`.
+/// Returns path to the stdout file.
+pub fn capture_to_file(
+    work_dir: &Path,
+    batch_id: &str,
+    index: usize,
+    stdout: &str,
+    stderr: &str,
+) -> std::io::Result<PathBuf> {
+    let capture_dir = work_dir.join(CAPTURE_DIR);
+    fs::create_dir_all(&capture_dir)?;
+    let stdout_file = capture_dir.join(format!("{batch_id}-{index}.stdout"));
+    fs::write(&stdout_file, stdout)?;
+    fs::write(
+        capture_dir.join(format!("{batch_id}-{index}.stderr")),
+        stderr,
+    )?;
+    Ok(stdout_file)
+}
+
+/// Removes [`CAPTURE_FILE_ARG`] from command arguments. Returns whether it was given.
+pub fn take_capture_file_arg(args: &[String]) -> (Vec<String>, bool) {
+    let capture_file = args.iter().any(|arg| arg == CAPTURE_FILE_ARG);
+    let args = args
+        .iter()
+        .filter(|arg| *arg != CAPTURE_FILE_ARG)
+        .cloned()
+        .collect();
+    (args, capture_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn str_output(captured: Option<Captured>) -> Option<String> {
+        match captured.and_then(Captured::into_result) {
+            Some(CommandOutput::Str(output)) => Some(output),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_capture_part() {
+        let output = b"0123456789";
+        assert_eq!(capture_part(&None, output), output);
+        assert_eq!(capture_part(&Some(CapturePart::Head(3)), output), b"012");
+        assert_eq!(capture_part(&Some(CapturePart::Tail(3)), output), b"789");
+        assert_eq!(
+            capture_part(&Some(CapturePart::HeadTail(5)), output),
+            b"01789"
+        );
+        assert_eq!(capture_part(&Some(CapturePart::Head(20)), output), output);
+        assert_eq!(capture_part(&Some(CapturePart::Tail(20)), output), output);
+        assert_eq!(
+            capture_part(&Some(CapturePart::HeadTail(20)), output),
+            output
+        );
+    }
+
+    #[test]
+    fn test_capture_with_mode() {
+        let at_end = CaptureMode::AtEnd {
+            part: Some(CapturePart::Tail(4)),
+            format: None,
+        };
+        assert!(matches!(
+            capture_with_mode(&at_end, "hash rate 12"),
+            Captured::AtEnd(CommandOutput::Str(output)) if output == "e 12"
+        ));
+
+        let stream = CaptureMode::Stream {
+            limit: Some(4),
+            format: Some(CaptureFormat::Bin),
+        };
+        assert!(matches!(
+            capture_with_mode(&stream, "hash rate 12"),
+            Captured::Stream(CommandOutput::Bin(output)) if output == b"hash"
+        ));
+
+        let capture = Some(Capture {
+            stdout: Some(stream),
+            stderr: Some(at_end),
+        });
+        let (stdout, stderr) = capture_output(&capture, "stdout", "stderr");
+        assert_eq!(str_output(stdout), None);
+        assert_eq!(str_output(stderr), Some("derr".to_string()));
+
+        let (stdout, stderr) = capture_output(&None, "stdout", "");
+        assert_eq!(str_output(stdout), Some("stdout".to_string()));
+        assert_eq!(str_output(stderr), Some("".to_string()));
+    }
+
+    #[test]
+    fn test_take_capture_file_arg() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            take_capture_file_arg(&args(&["--capture-file", "factory", "0x01"])),
+            (args(&["factory", "0x01"]), true)
+        );
+        assert_eq!(
+            take_capture_file_arg(&args(&["factory", "0x01"])),
+            (args(&["factory", "0x01"]), false)
+        );
+    }
+}
@@ -45,7 +45,7 @@ pub enum Command {
         /// ExeUnit daemon GSB URL
         report_url: String,
         #[command(flatten)]
-        args: RunArgs,
+        args: Box<RunArgs>,
     },
     /// Print an offer template in JSON format
     OfferTemplate,
@@ -64,4 +64,53 @@ pub struct RunArgs {
     /// Common cache directory
     #[arg(long, short)]
     pub cache_dir: PathBuf,
+    /// Maximum number of finished batches kept for `GetExecBatchResults`
+    #[arg(long, env = "CRUNCHER_BATCH_LIMIT", default_value_t = 100)]
+    pub batch_limit: usize,
+    /// Time in seconds for which results of a finished batch are kept
+    #[arg(long, env = "CRUNCHER_BATCH_TTL_SEC", default_value_t = 3600)]
+    pub batch_ttl_sec: u64,
+    /// Interval in seconds of checking whether client API lost work session after restart. 0 disables checks
+    #[arg(
+        long,
+        env = "CRUNCHER_SESSION_CHECK_INTERVAL_SEC",
+        default_value_t = 30
+    )]
+    pub session_check_interval_sec: u64,
+    /// Timeout in seconds of connecting to client API
+    #[arg(
+        long,
+        env = "CRUNCHER_CLIENT_API_CONNECT_TIMEOUT_SEC",
+        default_value_t = 5
+    )]
+    pub client_api_connect_timeout_sec: u64,
+    /// Timeout in seconds of a whole client API request
+    #[arg(long, env = "CRUNCHER_CLIENT_API_TIMEOUT_SEC", default_value_t = 30)]
+    pub client_api_timeout_sec: u64,
+    /// Number of retries of client API requests failed with connection error or 5xx response
+    #[arg(long, env = "CRUNCHER_CLIENT_API_RETRIES", default_value_t = 3)]
+    pub client_api_retries: u32,
+    /// Backoff in milliseconds before the first retry, doubled with each next one
+    #[arg(
+        long,
+        env = "CRUNCHER_CLIENT_API_RETRY_BACKOFF_MS",
+        default_value_t = 500
+    )]
+    pub client_api_retry_backoff_ms: u64,
+    /// Bearer token sent with client API requests
+    #[arg(long, env = "CRUNCHER_CLIENT_API_TOKEN", hide_env_values = true)]
+    pub client_api_token: Option<String>,
+    /// Shared secret of HMAC-SHA256 signatures of client API requests
+    #[arg(long, env = "CRUNCHER_CLIENT_API_HMAC_SECRET", hide_env_values = true)]
+    pub client_api_hmac_secret: Option<String>,
+    /// Interval in seconds of runners health checks, requested from client API `/api/runners/health`.
+    /// 0 disables checks
+    #[arg(long, env = "CRUNCHER_HEALTH_CHECK_INTERVAL_SEC", default_value_t = 0)]
+    pub health_check_interval_sec: u64,
+    /// Consecutive failed health checks after which runners are reported unhealthy
+    #[arg(long, env = "CRUNCHER_HEALTH_MAX_FAILURES", default_value_t = 3)]
+    pub health_max_failures: u32,
+    /// Time in seconds after which activity with unhealthy runners is terminated
+    #[arg(long, env = "CRUNCHER_HEALTH_TERMINATE_AFTER_SEC")]
+    pub health_terminate_after_sec: Option<u64>,
 }
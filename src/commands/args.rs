@@ -0,0 +1,251 @@
+//! Validation of Run command arguments against declared schema
+//!
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{CommandArg, CruncherCommand};
+use crate::error::CruncherError;
+
+/// Type of a single argument value.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArgKind {
+    String,
+    Number,
+    /// Hex digits, optionally prefixed with `0x`
+    Hex,
+}
+
+/// How arguments of a command are given.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArgsStyle {
+    /// Declared arguments in order, e.g. `factory 0x...`.
+    /// JSON object keys are argument names.
+    Positional,
+    /// Repeated `<name> <value>` pairs of declared arguments, e.g. `prefix 0xdead leading_zeros 4`.
+    /// JSON object keys are argument names.
+    Pairs,
+    /// Arguments are validated by the command itself, including JSON object form.
+    Custom,
+}
+
+/// Converts single JSON object argument into command arguments and validates them
+/// against schema declared by the command.
+pub fn check_args(
+    command: &dyn CruncherCommand,
+    args: Vec<String>,
+) -> Result<Vec<String>, CruncherError> {
+    let style = command.args_style();
+    if style == ArgsStyle::Custom {
+        return Ok(args);
+    }
+    let args = match args.as_slice() {
+        [arg] if arg.trim_start().starts_with('{') => from_json(command, arg)?,
+        _ => args,
+    };
+    match style {
+        ArgsStyle::Positional => check_positional(command, &args)?,
+        ArgsStyle::Pairs => check_pairs(command, &args)?,
+        ArgsStyle::Custom => (),
+    }
+    Ok(args)
+}
+
+fn from_json(command: &dyn CruncherCommand, arg: &str) -> Result<Vec<String>, CruncherError> {
+    let object = match serde_json::from_str::<Value>(arg) {
+        Ok(Value::Object(object)) => object,
+        Ok(_) => {
+            return Err(CruncherError::InvalidArgument(format!(
+                "JSON arguments of {} have to be an object",
+                command.name()
+            )))
+        }
+        Err(e) => {
+            return Err(CruncherError::InvalidArgument(format!(
+                "Invalid JSON arguments of {}: {e}",
+                command.name()
+            )))
+        }
+    };
+    if let Some(unknown) = object
+        .keys()
+        .find(|key| !command.args().iter().any(|arg| arg.name == key.as_str()))
+    {
+        return Err(CruncherError::InvalidArgument(format!(
+            "Unknown argument {unknown} of {}",
+            command.name()
+        )));
+    }
+
+    let mut args = Vec::new();
+    for (index, arg) in command.args().iter().enumerate() {
+        let Some(value) = object.get(arg.name) else {
+            continue;
+        };
+        let value = match value {
+            Value::String(value) => value.clone(),
+            Value::Number(value) => value.to_string(),
+            _ => {
+                return Err(CruncherError::InvalidArgument(format!(
+                    "Argument {} of {} has to be a string or a number",
+                    arg.name,
+                    command.name()
+                )))
+            }
+        };
+        match command.args_style() {
+            ArgsStyle::Pairs => args.extend([arg.name.to_string(), value]),
+            _ if args.len() < index => {
+                return Err(CruncherError::InvalidArgument(format!(
+                    "Argument {} of {} given without {}",
+                    arg.name,
+                    command.name(),
+                    command.args()[args.len()].name
+                )))
+            }
+            _ => args.push(value),
+        }
+    }
+    Ok(args)
+}
+
+fn check_positional(command: &dyn CruncherCommand, args: &[String]) -> Result<(), CruncherError> {
+    let declared = command.args();
+    if args.len() > declared.len() {
+        return Err(CruncherError::InvalidArgument(format!(
+            "{} accepts at most {} arguments, got {}: {}",
+            command.name(),
+            declared.len(),
+            args.len(),
+            args[declared.len()..].join(" ")
+        )));
+    }
+    if let Some(missing) = declared[args.len()..].iter().find(|arg| arg.required) {
+        return Err(CruncherError::InvalidArgument(format!(
+            "Missing argument {} of {}",
+            missing.name,
+            command.name()
+        )));
+    }
+    for (arg, value) in declared.iter().zip(args) {
+        check_value(command, arg, value)?;
+    }
+    Ok(())
+}
+
+fn check_pairs(command: &dyn CruncherCommand, args: &[String]) -> Result<(), CruncherError> {
+    for pair in args.chunks(2) {
+        let name = &pair[0];
+        let arg = command
+            .args()
+            .iter()
+            .find(|arg| arg.name == name.as_str())
+            .ok_or_else(|| {
+                CruncherError::InvalidArgument(format!(
+                    "Unknown argument {name} of {}",
+                    command.name()
+                ))
+            })?;
+        let value = pair.get(1).ok_or_else(|| {
+            CruncherError::InvalidArgument(format!(
+                "Missing value of argument {name} of {}",
+                command.name()
+            ))
+        })?;
+        check_value(command, arg, value)?;
+    }
+    if let Some(missing) = command
+        .args()
+        .iter()
+        .find(|arg| arg.required && !args.iter().step_by(2).any(|name| name == arg.name))
+    {
+        return Err(CruncherError::InvalidArgument(format!(
+            "Missing argument {} of {}",
+            missing.name,
+            command.name()
+        )));
+    }
+    Ok(())
+}
+
+fn check_value(
+    command: &dyn CruncherCommand,
+    arg: &CommandArg,
+    value: &str,
+) -> Result<(), CruncherError> {
+    let valid = match arg.kind {
+        ArgKind::String => true,
+        ArgKind::Number => value.parse::<f64>().is_ok_and(f64::is_finite),
+        ArgKind::Hex => {
+            let digits = value.strip_prefix("0x").unwrap_or(value);
+            !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit())
+        }
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(CruncherError::InvalidArgument(format!(
+            "Argument {} of {} expects {:?}, got {value:?}",
+            arg.name,
+            command.name(),
+            arg.kind
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::CommandRegistry;
+
+    fn check(command: &str, args: &[&str]) -> Result<Vec<String>, CruncherError> {
+        let registry = CommandRegistry::with_builtin();
+        let command = registry.get(command).unwrap();
+        check_args(
+            command.as_ref(),
+            args.iter().map(|arg| arg.to_string()).collect(),
+        )
+    }
+
+    #[test]
+    fn test_check_args() {
+        assert_eq!(check("set_hash", &["12.5"]), Ok(vec!["12.5".to_string()]));
+        assert_eq!(
+            check("set_hash", &[r#"{"tera_hash": 3}"#]),
+            Ok(vec!["3".to_string()])
+        );
+        assert!(check("set_hash", &["abc"]).is_err());
+        assert!(check("set_hash", &["1", "2"]).is_err());
+        assert!(check("set_hash", &[r#"{"hash": 3}"#]).is_err());
+        assert!(matches!(
+            check("set_hash", &["{3}"]),
+            Err(CruncherError::InvalidArgument(_))
+        ));
+
+        assert_eq!(
+            check(
+                "set_work_target",
+                &[r#"{"value": "0x01", "kind": "factory"}"#]
+            ),
+            Ok(vec!["factory".to_string(), "0x01".to_string()])
+        );
+        assert!(check("set_work_target", &["factory", "0xzz"]).is_err());
+
+        assert_eq!(
+            check(
+                "set_pattern",
+                &[r#"{"prefix": "dead", "leading_zeros": 4}"#]
+            ),
+            Ok(vec![
+                "prefix".to_string(),
+                "dead".to_string(),
+                "leading_zeros".to_string(),
+                "4".to_string()
+            ])
+        );
+        assert!(check("set_pattern", &["prefix"]).is_err());
+        assert!(check("set_pattern", &["suffix_len", "4"]).is_err());
+    }
+}
@@ -0,0 +1,487 @@
+use chrono::Utc;
+use futures::future::{FutureExt, LocalBoxFuture};
+use std::time::Duration;
+use ya_service_bus::typed as gsb;
+
+use super::{ArgKind, ArgsStyle, CommandArg, CommandRegistry, CruncherCommand, CONTINUE_ON_ERROR};
+use crate::error::CruncherError;
+use crate::pattern::Pattern;
+use crate::requests::{get_work_target, send_work_target};
+use crate::rotation::{parse_rotation, start_rotation, stop_rotation};
+use crate::session::{update_session, BackendTarget};
+use crate::work_target::{
+    apply_work_target, parse_work_target, sanitize_work_target, start_runners, stop_runners,
+};
+use crate::{set_usage_msg, ExeUnitContext};
+
+pub(super) fn register(registry: &mut CommandRegistry) {
+    registry
+        .register(ListCommands)
+        .register(SetHash)
+        .register(CheckAlive)
+        .register(SetWorkTarget)
+        .register(GetWorkTarget)
+        .register(SetWorkTargets)
+        .register(GetWorkTargetsUsage)
+        .register(SetPattern)
+        .register(StartWork)
+        .register(StopWork)
+        .register(ContinueOnError);
+}
+
+struct ListCommands;
+
+impl CruncherCommand for ListCommands {
+    fn name(&self) -> &'static str {
+        "list_commands"
+    }
+
+    fn description(&self) -> &'static str {
+        "Lists commands supported by the runtime in JSON format"
+    }
+
+    fn handle(
+        &self,
+        ctx: ExeUnitContext,
+        _args: Vec<String>,
+    ) -> LocalBoxFuture<'static, Result<String, CruncherError>> {
+        async move {
+            serde_json::to_string(&ctx.commands.usage())
+                .map_err(|e| CruncherError::Internal(format!("Failed to list commands: {e}")))
+        }
+        .boxed_local()
+    }
+}
+
+struct SetHash;
+
+impl CruncherCommand for SetHash {
+    fn name(&self) -> &'static str {
+        "set_hash"
+    }
+
+    fn description(&self) -> &'static str {
+        "Sets tera-hash usage counter and reports current usage"
+    }
+
+    fn args(&self) -> &'static [CommandArg] {
+        &[CommandArg {
+            name: "tera_hash",
+            description: "Number of tera-hashes computed so far",
+            kind: ArgKind::Number,
+            required: false,
+        }]
+    }
+
+    fn handle(
+        &self,
+        ctx: ExeUnitContext,
+        args: Vec<String>,
+    ) -> LocalBoxFuture<'static, Result<String, CruncherError>> {
+        async move {
+            if let Some(tera_hash) = args.first() {
+                let tera_hash = tera_hash.parse::<f64>().map_err(|e| {
+                    CruncherError::InvalidArgument(format!("Invalid tera_hash {tera_hash}: {e}"))
+                })?;
+                ctx.current_usage.lock().await[ctx.tera_hash_pos] = tera_hash;
+            }
+            set_usage_msg(
+                &gsb::service(ctx.report_url.clone()),
+                &ctx.activity_id,
+                ctx.current_usage.lock().await.clone(),
+            )
+            .await;
+            Ok(String::new())
+        }
+        .boxed_local()
+    }
+}
+
+struct CheckAlive;
+
+impl CruncherCommand for CheckAlive {
+    fn name(&self) -> &'static str {
+        "check_alive"
+    }
+
+    fn description(&self) -> &'static str {
+        "Responds with current runtime time"
+    }
+
+    fn handle(
+        &self,
+        _ctx: ExeUnitContext,
+        _args: Vec<String>,
+    ) -> LocalBoxFuture<'static, Result<String, CruncherError>> {
+        async move {
+            log::info!("Check alive command received");
+            Ok(format!("alive - {}", Utc::now()))
+        }
+        .boxed_local()
+    }
+}
+
+struct SetWorkTarget;
+
+impl CruncherCommand for SetWorkTarget {
+    fn name(&self) -> &'static str {
+        "set_work_target"
+    }
+
+    fn description(&self) -> &'static str {
+        "Sets work target of the cruncher runners"
+    }
+
+    fn args(&self) -> &'static [CommandArg] {
+        &[
+            CommandArg {
+                name: "kind",
+                description: "One of: factory, public_key_base, create2, default",
+                kind: ArgKind::String,
+                required: true,
+            },
+            CommandArg {
+                name: "value",
+                description: "Factory address, public key base or create2 deployer address (hex)",
+                kind: ArgKind::Hex,
+                required: false,
+            },
+            CommandArg {
+                name: "init_code_hash",
+                description: "create2 only: 32 bytes init code hash (hex)",
+                kind: ArgKind::Hex,
+                required: false,
+            },
+            CommandArg {
+                name: "salt_prefix",
+                description: "create2 only: fixed leading bytes of the salt (hex)",
+                kind: ArgKind::Hex,
+                required: false,
+            },
+        ]
+    }
+
+    fn handle(
+        &self,
+        ctx: ExeUnitContext,
+        args: Vec<String>,
+    ) -> LocalBoxFuture<'static, Result<String, CruncherError>> {
+        async move {
+            let work_target = parse_work_target(&args)?;
+
+            //sanitize command input for better security
+            let sanitized = sanitize_work_target(work_target)?;
+
+            log::info!("Setting work target to {}", sanitized);
+
+            stop_rotation(&ctx).await;
+            apply_work_target(&ctx, sanitized).await?;
+            Ok(String::new())
+        }
+        .boxed_local()
+    }
+}
+
+struct GetWorkTarget;
+
+impl CruncherCommand for GetWorkTarget {
+    fn name(&self) -> &'static str {
+        "get_work_target"
+    }
+
+    fn description(&self) -> &'static str {
+        "Responds with work target active in the client API and the last one set by the runtime. Mismatch is null when client API target is not understood"
+    }
+
+    fn handle(
+        &self,
+        ctx: ExeUnitContext,
+        _args: Vec<String>,
+    ) -> LocalBoxFuture<'static, Result<String, CruncherError>> {
+        async move {
+            let backend = get_work_target().await?;
+            let runtime = ctx.session.borrow().work_target.clone();
+
+            let mismatch = BackendTarget::parse(backend.clone()).mismatch(runtime.as_ref());
+            if mismatch == Some(true) {
+                log::warn!(
+                    "Client API work target {backend} differs from the last one set {runtime:?}"
+                );
+            }
+
+            serde_json::to_string(&serde_json::json!({
+                "backend": backend,
+                "runtime": runtime,
+                "mismatch": mismatch,
+            }))
+            .map_err(|e| CruncherError::Internal(format!("Failed to serialize work target: {e}")))
+        }
+        .boxed_local()
+    }
+}
+
+struct SetWorkTargets;
+
+impl CruncherCommand for SetWorkTargets {
+    fn name(&self) -> &'static str {
+        "set_work_targets"
+    }
+
+    fn description(&self) -> &'static str {
+        "Rotates work target of the cruncher runners between multiple targets"
+    }
+
+    fn args_style(&self) -> ArgsStyle {
+        ArgsStyle::Custom
+    }
+
+    fn args(&self) -> &'static [CommandArg] {
+        &[
+            CommandArg {
+                name: "--slice|--weight",
+                description: "Seconds or weight of the following work target in every rotation round. Repeated for each target",
+                kind: ArgKind::Number,
+                required: true,
+            },
+            CommandArg {
+                name: "work_target",
+                description: "Work target as in set_work_target, e.g. factory 0x.... Arguments can be given as JSON object with period and targets",
+                kind: ArgKind::String,
+                required: true,
+            },
+            CommandArg {
+                name: "--period",
+                description: "Seconds of rotation round split between weighted targets, 600 by default",
+                kind: ArgKind::Number,
+                required: false,
+            },
+        ]
+    }
+
+    fn handle(
+        &self,
+        ctx: ExeUnitContext,
+        args: Vec<String>,
+    ) -> LocalBoxFuture<'static, Result<String, CruncherError>> {
+        async move {
+            let entries = parse_rotation(&args)?;
+            for entry in &entries {
+                ctx.runtime_config.policy.check_work_target(&entry.target)?;
+            }
+            start_rotation(&ctx, entries).await;
+            Ok(String::new())
+        }
+        .boxed_local()
+    }
+}
+
+struct GetWorkTargetsUsage;
+
+impl CruncherCommand for GetWorkTargetsUsage {
+    fn name(&self) -> &'static str {
+        "get_work_targets_usage"
+    }
+
+    fn description(&self) -> &'static str {
+        "Responds with time and tera-hashes spent on each work target of the current rotation"
+    }
+
+    fn handle(
+        &self,
+        ctx: ExeUnitContext,
+        _args: Vec<String>,
+    ) -> LocalBoxFuture<'static, Result<String, CruncherError>> {
+        async move {
+            let tera_hash = ctx.current_usage.lock().await[ctx.tera_hash_pos];
+            let usage = ctx
+                .rotation
+                .borrow()
+                .as_ref()
+                .map(|rotation| rotation.borrow_mut().usage(tera_hash))
+                .unwrap_or_default();
+            serde_json::to_string(&usage)
+                .map_err(|e| CruncherError::Internal(format!("Failed to serialize usage: {e}")))
+        }
+        .boxed_local()
+    }
+}
+
+struct SetPattern;
+
+impl CruncherCommand for SetPattern {
+    fn name(&self) -> &'static str {
+        "set_pattern"
+    }
+
+    fn description(&self) -> &'static str {
+        "Sets pattern of addresses counted as hits, sent to client API together with the work target. Responds with estimated difficulty"
+    }
+
+    fn args_style(&self) -> ArgsStyle {
+        ArgsStyle::Pairs
+    }
+
+    fn args(&self) -> &'static [CommandArg] {
+        &[
+            CommandArg {
+                name: "prefix",
+                description: "Required leading hex characters of the address",
+                kind: ArgKind::Hex,
+                required: false,
+            },
+            CommandArg {
+                name: "suffix",
+                description: "Required trailing hex characters of the address",
+                kind: ArgKind::Hex,
+                required: false,
+            },
+            CommandArg {
+                name: "leading_zeros",
+                description: "Minimal number of leading zero characters",
+                kind: ArgKind::Number,
+                required: false,
+            },
+            CommandArg {
+                name: "mask",
+                description: "Characters allowed in the whole address, letters or digits",
+                kind: ArgKind::String,
+                required: false,
+            },
+            CommandArg {
+                name: "min_score",
+                description: "Minimal score of the address: zero characters count 1, leading zero characters count 2",
+                kind: ArgKind::Number,
+                required: false,
+            },
+        ]
+    }
+
+    fn handle(
+        &self,
+        ctx: ExeUnitContext,
+        args: Vec<String>,
+    ) -> LocalBoxFuture<'static, Result<String, CruncherError>> {
+        async move {
+            let pattern = Pattern::parse(&args)?.validate()?;
+            ctx.runtime_config.policy.check_pattern(&pattern)?;
+            let difficulty = pattern.difficulty();
+            log::info!(
+                "Setting pattern to {:?}, difficulty {:e}",
+                pattern,
+                difficulty
+            );
+
+            let _lock = ctx.work_target_lock.lock().await;
+            let work_target = ctx.session.borrow().work_target.clone();
+            if let Some(work_target) = work_target {
+                send_work_target(work_target, Some(&pattern)).await?;
+            }
+            update_session(&ctx, |session| session.pattern = Some(pattern.clone()))?;
+            serde_json::to_string(&serde_json::json!({
+                "pattern": pattern,
+                "difficulty": difficulty,
+            }))
+            .map_err(|e| CruncherError::Internal(format!("Failed to serialize pattern: {e}")))
+        }
+        .boxed_local()
+    }
+}
+
+struct StartWork;
+
+impl CruncherCommand for StartWork {
+    fn name(&self) -> &'static str {
+        "start_work"
+    }
+
+    fn description(&self) -> &'static str {
+        "Starts cruncher runners. Responds with status of each runner"
+    }
+
+    fn args(&self) -> &'static [CommandArg] {
+        &[CommandArg {
+            name: "follow_sec",
+            description:
+                "Seconds to keep the command running after runners start, reporting runners status polled from client API",
+            kind: ArgKind::Number,
+            required: false,
+        }]
+    }
+
+    fn reports_progress(&self) -> bool {
+        true
+    }
+
+    fn handle(
+        &self,
+        ctx: ExeUnitContext,
+        args: Vec<String>,
+    ) -> LocalBoxFuture<'static, Result<String, CruncherError>> {
+        async move {
+            let follow = args
+                .first()
+                .map(|follow| {
+                    follow
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(|follow| Duration::try_from_secs_f64(follow).ok())
+                        .ok_or_else(|| {
+                            CruncherError::InvalidArgument(format!(
+                                "follow_sec expects non-negative number of seconds, got {follow}"
+                            ))
+                        })
+                })
+                .transpose()?;
+            let runners = start_runners(&ctx).await?;
+            if let Some(follow) = follow {
+                tokio::time::sleep(follow).await;
+            }
+            Ok(runners.to_stdout())
+        }
+        .boxed_local()
+    }
+}
+
+struct StopWork;
+
+impl CruncherCommand for StopWork {
+    fn name(&self) -> &'static str {
+        "stop_work"
+    }
+
+    fn description(&self) -> &'static str {
+        "Stops cruncher runners and work target rotation. Responds with status of each runner"
+    }
+
+    fn handle(
+        &self,
+        ctx: ExeUnitContext,
+        _args: Vec<String>,
+    ) -> LocalBoxFuture<'static, Result<String, CruncherError>> {
+        async move {
+            stop_rotation(&ctx).await;
+            Ok(stop_runners(&ctx).await?.to_stdout())
+        }
+        .boxed_local()
+    }
+}
+
+struct ContinueOnError;
+
+impl CruncherCommand for ContinueOnError {
+    fn name(&self) -> &'static str {
+        CONTINUE_ON_ERROR
+    }
+
+    fn description(&self) -> &'static str {
+        "Makes the batch containing this command execute remaining commands after one of them fails"
+    }
+
+    fn handle(
+        &self,
+        _ctx: ExeUnitContext,
+        _args: Vec<String>,
+    ) -> LocalBoxFuture<'static, Result<String, CruncherError>> {
+        async move { Ok(String::new()) }.boxed_local()
+    }
+}
@@ -0,0 +1,119 @@
+//! Run commands understood by the cruncher runtime
+//!
+
+use futures::future::LocalBoxFuture;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::error::CruncherError;
+use crate::ExeUnitContext;
+
+mod args;
+mod builtin;
+
+pub use args::{ArgKind, ArgsStyle};
+
+/// Entry point of command making its batch continue after failed commands
+pub const CONTINUE_ON_ERROR: &str = "continue_on_error";
+
+/// Single argument accepted by a [`CruncherCommand`].
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandArg {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub kind: ArgKind,
+    pub required: bool,
+}
+
+/// Handler of a single `ExeScriptCommand::Run` entry point.
+pub trait CruncherCommand {
+    /// Entry point under which the command is registered.
+    fn name(&self) -> &'static str;
+    /// Short, human readable description of the command.
+    fn description(&self) -> &'static str;
+    /// Arguments accepted by the command.
+    fn args(&self) -> &'static [CommandArg] {
+        &[]
+    }
+    /// How [`CruncherCommand::args`] are given.
+    fn args_style(&self) -> ArgsStyle {
+        ArgsStyle::Positional
+    }
+    /// Whether runners status polled from client API is emitted as runtime events
+    /// while the command runs.
+    fn reports_progress(&self) -> bool {
+        false
+    }
+    /// Executes the command with arguments checked against the declared schema.
+    /// Returned string is used as command stdout.
+    fn handle(
+        &self,
+        ctx: ExeUnitContext,
+        args: Vec<String>,
+    ) -> LocalBoxFuture<'static, Result<String, CruncherError>>;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandUsage {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub args: &'static [CommandArg],
+    pub args_style: ArgsStyle,
+}
+
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: BTreeMap<&'static str, Rc<dyn CruncherCommand>>,
+}
+
+impl CommandRegistry {
+    /// Registry with all commands built into the runtime.
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::default();
+        builtin::register(&mut registry);
+        registry
+    }
+
+    /// Registers `command`, replacing previously registered command with the same name.
+    pub fn register(&mut self, command: impl CruncherCommand + 'static) -> &mut Self {
+        let command = Rc::new(command);
+        if self.commands.insert(command.name(), command).is_some() {
+            log::warn!("Command registered more than once, using the last one");
+        }
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<Rc<dyn CruncherCommand>> {
+        self.commands.get(name).cloned()
+    }
+
+    pub fn usage(&self) -> Vec<CommandUsage> {
+        self.commands
+            .values()
+            .map(|command| CommandUsage {
+                name: command.name(),
+                description: command.description(),
+                args: command.args(),
+                args_style: command.args_style(),
+            })
+            .collect()
+    }
+
+    pub async fn run(
+        &self,
+        ctx: ExeUnitContext,
+        name: &str,
+        args: Vec<String>,
+    ) -> Result<String, CruncherError> {
+        let command = self.get(name).ok_or_else(|| {
+            log::error!("Invalid command for cruncher runtime: {:?}", name);
+            CruncherError::UnsupportedCommand(format!(
+                "invalid command for cruncher runtime: {:?}",
+                name
+            ))
+        })?;
+        let args = args::check_args(command.as_ref(), args)?;
+        command.handle(ctx, args).await
+    }
+}
@@ -0,0 +1,35 @@
+//! Runtime configuration passed with `--runtime-config`
+//!
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::auth::ClientApiAuth;
+use crate::policy::Policy;
+
+/// Runtime part of ExeUnit descriptor. Unknown fields are ignored.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RuntimeConfig {
+    /// Usage counters declared in ExeUnit descriptor
+    pub counters: BTreeMap<String, serde_json::Value>,
+    /// Provider restrictions of work targets and patterns
+    pub policy: Policy,
+    /// Credentials of client API requests, unless given with env
+    pub client_api_auth: Option<ClientApiAuth>,
+}
+
+impl RuntimeConfig {
+    pub fn from_value(runtime_config: Option<&serde_json::Value>) -> anyhow::Result<Self> {
+        let config: Self = match runtime_config {
+            Some(runtime_config) => serde_json::from_value(runtime_config.clone())
+                .map_err(|e| anyhow::anyhow!("Invalid runtime config: {e}"))?,
+            None => Self::default(),
+        };
+        let policy = config
+            .policy
+            .normalize()
+            .map_err(|e| anyhow::anyhow!("Invalid runtime config policy: {e}"))?;
+        Ok(Self { policy, ..config })
+    }
+}
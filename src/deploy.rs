@@ -0,0 +1,69 @@
+//! Readiness checks performed on `ExeScriptCommand::Deploy`
+//!
+
+use crate::config::RuntimeConfig;
+use crate::error::CruncherError;
+use crate::requests::check_client_api;
+use crate::ExeUnitContext;
+
+pub async fn deploy(ctx: &ExeUnitContext) -> Result<(), CruncherError> {
+    check_usage_counters(&ctx.counters, &ctx.runtime_config)?;
+    check_client_api().await?;
+    log::info!("Deploy readiness checks passed");
+    Ok(())
+}
+
+/// Checks agreement counters against runtime config. Presence of tera-hash and duration
+/// counters is already validated when the runtime starts.
+fn check_usage_counters(
+    counters: &[String],
+    runtime_config: &RuntimeConfig,
+) -> Result<(), CruncherError> {
+    // Descriptor without counters section does not restrict agreement counters
+    if runtime_config.counters.is_empty() {
+        return Ok(());
+    }
+    match counters
+        .iter()
+        .find(|counter| !runtime_config.counters.contains_key(*counter))
+    {
+        Some(counter) => Err(CruncherError::Internal(format!(
+            "Invalid agreement. Usage counter {counter} not supported by runtime config"
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_usage_counters() {
+        let counters = vec![
+            "golem.usage.duration_sec".to_string(),
+            "golem.usage.tera-hash".to_string(),
+        ];
+        let config = |counters: serde_json::Value| {
+            RuntimeConfig::from_value(Some(&serde_json::json!({ "counters": counters }))).unwrap()
+        };
+
+        assert_eq!(
+            check_usage_counters(&counters, &RuntimeConfig::default()),
+            Ok(())
+        );
+        let supported = config(serde_json::json!({
+            "golem.usage.duration_sec": {"name": "duration", "description": "Duration"},
+            "golem.usage.tera-hash": {"name": "tera-hash", "description": "Tera hashes"},
+        }));
+        assert_eq!(check_usage_counters(&counters, &supported), Ok(()));
+
+        let missing = config(serde_json::json!({
+            "golem.usage.duration_sec": {"name": "duration", "description": "Duration"},
+        }));
+        assert!(matches!(
+            check_usage_counters(&counters, &missing),
+            Err(CruncherError::Internal(message)) if message.contains("golem.usage.tera-hash")
+        ));
+    }
+}
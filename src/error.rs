@@ -0,0 +1,27 @@
+//! Errors of cruncher commands
+//!
+
+/// Error of a cruncher command.
+/// Displayed message starts with a stable, machine readable code, e.g.
+/// `[INVALID_ARGUMENT] Wrong factory data len`.
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum CruncherError {
+    /// Command arguments are missing or malformed
+    #[error("[INVALID_ARGUMENT] {0}")]
+    InvalidArgument(String),
+    /// Cruncher client API cannot be reached
+    #[error("[BACKEND_UNREACHABLE] {0}")]
+    BackendUnreachable(String),
+    /// Cruncher client API responded with an error
+    #[error("[BACKEND_REJECTED] {0}")]
+    BackendRejected(String),
+    /// Command is not allowed by provider policy
+    #[error("[POLICY_VIOLATION] {0}")]
+    PolicyViolation(String),
+    /// Command is not supported by the runtime
+    #[error("[UNSUPPORTED_COMMAND] {0}")]
+    UnsupportedCommand(String),
+    /// Failure of the runtime itself
+    #[error("[INTERNAL] {0}")]
+    Internal(String),
+}
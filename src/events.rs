@@ -0,0 +1,43 @@
+//! Runtime events of running batches, served with `StreamExecBatchResults`
+//!
+
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use ya_client_model::activity::RuntimeEvent;
+
+#[derive(Clone, Default)]
+pub struct BatchEvents {
+    subscribers: Rc<RefCell<HashMap<String, Vec<UnboundedSender<RuntimeEvent>>>>>,
+}
+
+impl BatchEvents {
+    /// Marks batch as running. Events can be subscribed only for running batches.
+    pub fn start(&self, batch_id: &str) {
+        self.subscribers
+            .borrow_mut()
+            .insert(batch_id.to_string(), Vec::new());
+    }
+
+    /// Closes all subscriptions of the batch.
+    pub fn finish(&self, batch_id: &str) {
+        self.subscribers.borrow_mut().remove(batch_id);
+    }
+
+    /// Returns `None` when batch is not running.
+    pub fn subscribe(&self, batch_id: &str) -> Option<UnboundedReceiver<RuntimeEvent>> {
+        let mut subscribers = self.subscribers.borrow_mut();
+        let batch_subscribers = subscribers.get_mut(batch_id)?;
+        let (tx, rx) = unbounded();
+        batch_subscribers.push(tx);
+        Some(rx)
+    }
+
+    pub fn emit(&self, event: RuntimeEvent) {
+        let mut subscribers = self.subscribers.borrow_mut();
+        if let Some(batch_subscribers) = subscribers.get_mut(&event.batch_id) {
+            batch_subscribers.retain(|tx| tx.unbounded_send(event.clone()).is_ok());
+        }
+    }
+}
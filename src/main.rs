@@ -6,12 +6,17 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::io;
 use std::io::Write;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::{mpsc, mpsc::Receiver, mpsc::Sender, Mutex};
+use tokio::task::JoinHandle;
 use ya_client_model::activity::activity_state::*;
 use ya_client_model::activity::{ActivityUsage, CommandResult, ExeScriptCommandResult};
-use ya_client_model::activity::{CommandOutput, ExeScriptCommand};
+use ya_client_model::activity::{
+    CommandOutput, ExeScriptCommand, ExeScriptCommandState, RuntimeEvent,
+};
 use ya_core_model::activity;
 use ya_core_model::activity::exeunit::bus_id;
 use ya_core_model::activity::RpcMessageError;
@@ -19,22 +24,57 @@ use ya_service_bus::typed::{self as gsb, Endpoint};
 use ya_transfer::transfer::{Shutdown, TransferService, TransferServiceContext};
 
 use crate::agreement::AgreementDesc;
+use crate::auth::ClientApiAuth;
+use crate::batches::{empty_batch_result, is_batch_finished, BatchStore};
+use crate::capture::{capture_output, capture_to_file, take_capture_file_arg, Captured};
 use crate::cli::*;
+use crate::commands::{CommandRegistry, CONTINUE_ON_ERROR};
+use crate::config::RuntimeConfig;
+use crate::deploy::deploy;
+use crate::error::CruncherError;
+use crate::events::BatchEvents;
 use crate::logger::*;
-use crate::requests::{init_client_api_url, send_work_target, start_work, stop_work, WorkTarget};
+use crate::progress::{spawn_progress_emitter, PROGRESS_INTERVAL};
+use crate::requests::{
+    get_runners, init_client_api_url, init_http_client, HttpClientConfig, RetryConfig,
+};
+use crate::rotation::Rotation;
+use crate::session::{spawn_session_monitor, spawn_session_restore, Session};
 use crate::signal::SignalMonitor;
+use crate::start::{spawn_usage_reporter, StartArgs};
+use crate::transfer::transfer;
+use crate::watchdog::{spawn_health_watchdog, WatchdogConfig};
+use crate::work_target::{apply_work_target, start_runners};
 
 mod agreement;
+mod auth;
+mod batches;
+mod capture;
 mod cli;
+mod commands;
+mod config;
+mod deploy;
+mod error;
+mod events;
 mod logger;
 mod offer_template;
+mod pattern;
+mod policy;
 mod process;
+mod progress;
 mod requests;
+mod rotation;
+mod session;
 mod signal;
+mod start;
+mod transfer;
+mod watchdog;
+mod work_target;
 
 pub type Signal = &'static str;
 
 async fn send_state(ctx: &ExeUnitContext, new_state: ActivityState) -> anyhow::Result<()> {
+    *ctx.state.borrow_mut() = new_state.clone();
     Ok(gsb::service(ctx.report_url.clone())
         .call(activity::local::SetState::new(
             ctx.activity_id.clone(),
@@ -141,183 +181,193 @@ async fn handle_signals(signal_receiver: Sender<Signal>) -> anyhow::Result<()> {
 struct ExeUnitContext {
     pub activity_id: String,
     pub report_url: String,
+    pub work_dir: PathBuf,
     pub transfers: Addr<TransferService>,
-    pub batches: Rc<RefCell<HashMap<String, Vec<ExeScriptCommandResult>>>>,
+    pub batches: Rc<RefCell<BatchStore>>,
+    pub events: BatchEvents,
+    pub state: Rc<RefCell<ActivityState>>,
+    pub running_commands: Rc<RefCell<HashMap<String, ExeScriptCommandState>>>,
+    pub commands: Rc<CommandRegistry>,
+    pub current_usage: Arc<Mutex<Vec<f64>>>,
+    /// Work target and runners state persisted in `work_dir`
+    pub session: Rc<RefCell<Session>>,
+    /// Work targets rotation driven by timer
+    pub rotation: Rc<RefCell<Option<Rc<RefCell<Rotation>>>>>,
+    /// Held while work target is sent to client API and saved in the session
+    pub work_target_lock: Rc<Mutex<()>>,
+    /// Periodic usage reporter started with `--usage-interval`
+    pub usage_reporter: Rc<RefCell<Option<JoinHandle<()>>>>,
+    pub counters: Vec<String>,
+    pub tera_hash_pos: usize,
+    pub runtime_config: Rc<RuntimeConfig>,
 }
 
-async fn prepare_script_future(
-    ctx: ExeUnitContext,
-    exec: activity::Exec,
-    current_usage: Arc<Mutex<Vec<f64>>>,
-    tera_hash_pos: usize,
-) -> Result<String, RpcMessageError> {
-    let mut result = Vec::new();
-    for exe in &exec.exe_script {
-        match exe {
-            ExeScriptCommand::Deploy { .. } => {}
-            ExeScriptCommand::Start { args, .. } => {
-                log::debug!("Raw Start cmd args: {args:?} [ignored]");
-
-                set_usage_msg(
-                    &gsb::service(ctx.report_url.clone()),
-                    &ctx.activity_id,
-                    current_usage.lock().await.clone(),
-                )
-                .await;
-
-                send_state(
-                    &ctx,
-                    ActivityState::from(StatePair(State::Ready, Some(State::Ready))),
-                )
+/// Output of successfully executed `ExeScriptCommand`.
+#[derive(Default)]
+struct CommandOutcome {
+    stdout: Option<CommandOutput>,
+    stderr: Option<CommandOutput>,
+    message: Option<String>,
+}
+
+async fn execute_command(
+    ctx: &ExeUnitContext,
+    batch_id: &str,
+    index: usize,
+    exe: &ExeScriptCommand,
+) -> Result<CommandOutcome, CruncherError> {
+    match exe {
+        ExeScriptCommand::Deploy { .. } => {
+            deploy(ctx).await?;
+            send_state(ctx, ActivityState::from(StatePair(State::Deployed, None)))
                 .await
-                .map_err(|e| RpcMessageError::Service(e.to_string()))?;
+                .map_err(|e| CruncherError::Internal(e.to_string()))?;
 
-                log::info!("Got start command, changing state of exe unit to ready",);
-                result.push(ExeScriptCommandResult {
-                    index: result.len() as u32,
-                    result: CommandResult::Ok,
-                    stdout: None,
-                    stderr: None,
-                    message: None,
-                    is_batch_finished: true,
-                    event_date: Utc::now(),
-                })
+            log::info!("Got deploy command, changing state of exe unit to deployed");
+            Ok(CommandOutcome::default())
+        }
+        ExeScriptCommand::Start { args, .. } => {
+            log::debug!("Raw Start cmd args: {args:?}");
+            let start_args = StartArgs::parse(args)?;
+
+            if let Some(work_target) = start_args.work_target {
+                log::info!("Setting initial work target to {}", work_target);
+                apply_work_target(ctx, work_target).await?;
+            }
+            if start_args.auto_start {
+                start_runners(ctx).await?;
+            }
+            if let Some(interval) = start_args.usage_interval {
+                spawn_usage_reporter(ctx, interval);
             }
-            ExeScriptCommand::Terminate { .. } => {
-                log::info!("Raw Terminate command. Stopping runtime",);
-
-                ctx.transfers.send(Shutdown {}).await.ok();
-                send_state(
-                    &ctx,
-                    ActivityState::from(StatePair(State::Terminated, None)),
-                )
+
+            set_usage_msg(
+                &gsb::service(ctx.report_url.clone()),
+                &ctx.activity_id,
+                ctx.current_usage.lock().await.clone(),
+            )
+            .await;
+
+            send_state(
+                ctx,
+                ActivityState::from(StatePair(State::Ready, Some(State::Ready))),
+            )
+            .await
+            .map_err(|e| CruncherError::Internal(e.to_string()))?;
+
+            log::info!("Got start command, changing state of exe unit to ready",);
+            Ok(CommandOutcome::default())
+        }
+        ExeScriptCommand::Terminate { .. } => {
+            log::info!("Raw Terminate command. Stopping runtime",);
+
+            ctx.transfers.send(Shutdown {}).await.ok();
+            send_state(ctx, ActivityState::from(StatePair(State::Terminated, None)))
                 .await
-                .map_err(|e| RpcMessageError::Service(e.to_string()))?;
-                result.push(ExeScriptCommandResult {
-                    index: result.len() as u32,
-                    result: CommandResult::Ok,
-                    stdout: None,
-                    stderr: None,
-                    message: None,
-                    is_batch_finished: false,
-                    event_date: Utc::now(),
+                .map_err(|e| CruncherError::Internal(e.to_string()))?;
+            Ok(CommandOutcome::default())
+        }
+        ExeScriptCommand::Run {
+            entry_point,
+            args,
+            capture,
+        } => {
+            let command = entry_point;
+            log::info!("Receive command {command} with args {}", args.join(" "));
+            let (args, capture_file) = take_capture_file_arg(args);
+
+            let progress = ctx
+                .commands
+                .get(command)
+                .filter(|command| command.reports_progress())
+                .map(|_| {
+                    spawn_progress_emitter(
+                        ctx.events.clone(),
+                        batch_id.to_string(),
+                        index,
+                        PROGRESS_INTERVAL,
+                        get_runners,
+                    )
                 });
+            let commands = ctx.commands.clone();
+            let result = commands.run(ctx.clone(), command, args).await;
+            if let Some(progress) = progress {
+                progress.abort();
             }
-            ExeScriptCommand::Run {
-                entry_point,
-                args,
-                capture,
-            } => {
-                //mark capture as unused
-                let _capture = capture;
-                log::debug!("Parameter capture ignored");
-
-                let command = entry_point;
-                log::info!("Receive command {command} with args {}", args.join(" "));
-
-                let mut stdout_message = String::new();
-                if command == "set_hash" {
-                    {
-                        if let Some(tera_hash) = args.first() {
-                            if let Ok(tera_hash) = tera_hash.parse::<f64>() {
-                                current_usage.lock().await[tera_hash_pos] = tera_hash;
-                            }
-                        }
-                    }
-                    set_usage_msg(
-                        &gsb::service(ctx.report_url.clone()),
-                        &ctx.activity_id,
-                        current_usage.lock().await.clone(),
-                    )
-                    .await;
-                } else if command == "check_alive" {
-                    log::info!("Check alive command received");
-                    stdout_message = format!("alive - {}", Utc::now());
-                } else if command == "set_work_target" {
-                    let first_arg = args.first().ok_or_else(|| {
-                        RpcMessageError::Activity("Missing work target arg".to_string())
-                    })?;
-
-                    let work_target = if first_arg == "factory" {
-                        let sec_arg = args.get(1).ok_or_else(|| {
-                            RpcMessageError::Activity("Missing factory arg".to_string())
-                        })?;
-                        WorkTarget::Factory(sec_arg.to_string())
-                    } else if first_arg == "public_key_base" {
-                        let sec_arg = args.get(1).ok_or_else(|| {
-                            RpcMessageError::Activity("Missing public key base arg".to_string())
-                        })?;
-                        WorkTarget::PublicKeyBase(sec_arg.to_string())
-                    } else if first_arg == "default" {
-                        WorkTarget::Default
-                    } else {
-                        return Err(RpcMessageError::Activity("Unknown work target".to_string()));
-                    };
-
-                    //sanitize command input for better security
-
-                    let sanitized = match work_target {
-                        WorkTarget::Factory(f) => {
-                            let slice = hex::decode(f.replace("0x", "")).map_err(|_| {
-                                RpcMessageError::Activity("Invalid factory public key".to_string())
-                            })?;
-                            if slice.len() != 20 {
-                                return Err(RpcMessageError::Activity(
-                                    "Wrong factory data len".to_string(),
-                                ));
-                            };
-                            let factory = format!("0x{}", hex::encode(slice));
-                            WorkTarget::Factory(factory)
-                        }
-                        WorkTarget::PublicKeyBase(p) => {
-                            let slice = hex::decode(p.replace("0x", "")).map_err(|_| {
-                                RpcMessageError::Activity("Invalid public key base".to_string())
-                            })?;
-                            if slice.len() != 64 {
-                                return Err(RpcMessageError::Activity(
-                                    "Wrong public key base data len".to_string(),
-                                ));
-                            };
-                            let public_key_base = format!("0x{}", hex::encode(slice));
-                            WorkTarget::PublicKeyBase(public_key_base)
-                        }
-                        WorkTarget::Default => WorkTarget::Default,
-                    };
-
-                    log::info!("Setting work target to {:?}", sanitized);
-
-                    send_work_target(sanitized).await?;
-                } else if command == "start_work" {
-                    start_work().await?
-                } else if command == "stop_work" {
-                    stop_work().await?
-                } else {
-                    log::error!("Invalid command for cruncher runtime: {:?}", command);
-                    return Err(RpcMessageError::Activity(format!(
-                        "invalid command for cruncher runtime: {:?}",
-                        command
-                    )));
+            let stdout_message = result?;
+
+            if capture.is_none() && !stdout_message.is_empty() {
+                ctx.events.emit(RuntimeEvent::stdout(
+                    batch_id.to_string(),
+                    index,
+                    CommandOutput::Str(stdout_message.clone()),
+                ));
+            }
+            if capture_file {
+                match capture_to_file(&ctx.work_dir, batch_id, index, &stdout_message, "") {
+                    Ok(path) => log::debug!("Command output captured to {}", path.display()),
+                    Err(e) => log::warn!("Failed to capture command output to file: {e}"),
                 }
-
-                result.push(ExeScriptCommandResult {
-                    index: result.len() as u32,
-                    result: CommandResult::Ok,
-                    stdout: Some(CommandOutput::Str(stdout_message)),
-                    stderr: Some(CommandOutput::Str("".to_string())),
-                    message: Some("Ok".to_string()),
-                    is_batch_finished: true,
-                    event_date: Utc::now(),
-                });
             }
-            cmd => {
-                log::error!("invalid command for ai runtime: {:?}", cmd);
-                return Err(RpcMessageError::Activity(format!(
-                    "invalid command for ai runtime: {:?}",
-                    cmd
-                )));
+            let (stdout, stderr) = capture_output(capture, &stdout_message, "");
+            if let Some(Captured::Stream(output)) = &stdout {
+                ctx.events.emit(RuntimeEvent::stdout(
+                    batch_id.to_string(),
+                    index,
+                    output.clone(),
+                ));
+            }
+            if let Some(Captured::Stream(output)) = &stderr {
+                ctx.events.emit(RuntimeEvent::stderr(
+                    batch_id.to_string(),
+                    index,
+                    output.clone(),
+                ));
             }
+
+            Ok(CommandOutcome {
+                stdout: stdout.and_then(Captured::into_result),
+                stderr: stderr.and_then(Captured::into_result),
+                message: Some("Ok".to_string()),
+            })
+        }
+        ExeScriptCommand::Transfer { from, to, args, .. } => {
+            transfer(&ctx.transfers, &ctx.work_dir, from, to, args).await?;
+            Ok(CommandOutcome::default())
+        }
+        cmd => {
+            log::error!("invalid command for ai runtime: {:?}", cmd);
+            Err(CruncherError::UnsupportedCommand(format!(
+                "invalid command for ai runtime: {:?}",
+                cmd
+            )))
+        }
+    }
+}
+
+fn command_state(exe: &ExeScriptCommand) -> ExeScriptCommandState {
+    let (command, params) = match exe {
+        ExeScriptCommand::Sign { .. } => ("sign".to_string(), None),
+        ExeScriptCommand::Deploy { .. } => ("deploy".to_string(), None),
+        ExeScriptCommand::Start { args } => ("start".to_string(), Some(args.clone())),
+        ExeScriptCommand::Run {
+            entry_point, args, ..
+        } => (entry_point.clone(), Some(args.clone())),
+        ExeScriptCommand::Transfer { from, to, .. } => {
+            ("transfer".to_string(), Some(vec![from.clone(), to.clone()]))
         }
+        ExeScriptCommand::Terminate { .. } => ("terminate".to_string(), None),
+    };
+    ExeScriptCommandState {
+        command,
+        progress: None,
+        params,
     }
+}
+
+/// Executes batch commands in order, storing result of each command as soon as it finishes.
+/// Batch is stopped on first failure, unless it contains `continue_on_error` command.
+async fn prepare_script_future(ctx: ExeUnitContext, exec: activity::Exec) {
     log::info!(
         "got exec {}, batch_id={}, script={:?}",
         exec.activity_id,
@@ -325,14 +375,78 @@ async fn prepare_script_future(
         exec.exe_script
     );
 
-    {
-        let _ = ctx
-            .batches
+    if exec.exe_script.is_empty() {
+        ctx.batches
             .borrow_mut()
-            .insert(exec.batch_id.clone(), result);
+            .push(&exec.batch_id, empty_batch_result());
+        return;
     }
+    let continue_on_error = exec.exe_script.iter().any(|exe| {
+        matches!(exe, ExeScriptCommand::Run { entry_point, .. } if entry_point == CONTINUE_ON_ERROR)
+    });
+    for (index, exe) in exec.exe_script.iter().enumerate() {
+        ctx.events.emit(RuntimeEvent::started(
+            exec.batch_id.clone(),
+            index,
+            exe.clone(),
+        ));
+
+        ctx.running_commands
+            .borrow_mut()
+            .insert(exec.batch_id.clone(), command_state(exe));
+        let outcome = execute_command(&ctx, &exec.batch_id, index, exe).await;
+        ctx.running_commands.borrow_mut().remove(&exec.batch_id);
+        let is_batch_finished = is_batch_finished(
+            index,
+            exec.exe_script.len(),
+            outcome.is_err(),
+            continue_on_error,
+        );
 
-    Ok(exec.batch_id)
+        let result = match outcome {
+            Ok(outcome) => {
+                ctx.events.emit(RuntimeEvent::finished(
+                    exec.batch_id.clone(),
+                    index,
+                    0,
+                    None,
+                ));
+                ExeScriptCommandResult {
+                    index: index as u32,
+                    result: CommandResult::Ok,
+                    stdout: outcome.stdout,
+                    stderr: outcome.stderr,
+                    message: outcome.message,
+                    is_batch_finished,
+                    event_date: Utc::now(),
+                }
+            }
+            Err(e) => {
+                log::error!("ExeScript failure: {e:?}");
+                ctx.events.emit(RuntimeEvent::finished(
+                    exec.batch_id.clone(),
+                    index,
+                    1,
+                    Some(e.to_string()),
+                ));
+                ExeScriptCommandResult {
+                    index: index as u32,
+                    result: CommandResult::Error,
+                    stdout: None,
+                    stderr: None,
+                    message: Some(e.to_string()),
+                    is_batch_finished,
+                    event_date: Utc::now(),
+                }
+            }
+        };
+
+        ctx.batches.borrow_mut().push(&exec.batch_id, result);
+
+        if is_batch_finished {
+            break;
+        }
+    }
 }
 
 async fn run(cli: Cli, mut signal_receiver: Receiver<Signal>) -> anyhow::Result<()> {
@@ -358,6 +472,22 @@ async fn run(cli: Cli, mut signal_receiver: Receiver<Signal>) -> anyhow::Result<
         }
     };
 
+    let runtime_config = RuntimeConfig::from_value(cli.runtime_config.as_ref())?;
+
+    init_http_client(HttpClientConfig {
+        connect_timeout: Duration::from_secs(args.client_api_connect_timeout_sec),
+        timeout: Duration::from_secs(args.client_api_timeout_sec),
+        retry: RetryConfig {
+            max_retries: args.client_api_retries,
+            initial_backoff: Duration::from_millis(args.client_api_retry_backoff_ms),
+        },
+        auth: ClientApiAuth::resolve(
+            args.client_api_token.clone(),
+            args.client_api_hmac_secret.clone(),
+            runtime_config.client_api_auth.clone(),
+        )?,
+    })?;
+
     let agreement_path = args.agreement.clone();
 
     let agreement = AgreementDesc::load(agreement_path)?;
@@ -389,69 +519,112 @@ async fn run(cli: Cli, mut signal_receiver: Receiver<Signal>) -> anyhow::Result<
     let ctx = ExeUnitContext {
         activity_id: activity_id.clone(),
         report_url: report_url.clone(),
+        work_dir: args.work_dir.clone(),
         transfers: TransferService::new(TransferServiceContext {
             work_dir: args.work_dir.clone(),
             cache_dir: args.cache_dir.clone(),
             ..TransferServiceContext::default()
         })
         .start(),
-        batches: Rc::new(RefCell::new(Default::default())),
+        batches: Rc::new(RefCell::new(BatchStore::new(
+            args.batch_limit,
+            Duration::from_secs(args.batch_ttl_sec),
+        ))),
+        events: BatchEvents::default(),
+        state: Rc::new(RefCell::new(ActivityState::from(StatePair(
+            State::New,
+            None,
+        )))),
+        running_commands: Rc::new(RefCell::new(Default::default())),
+        commands: Rc::new(CommandRegistry::with_builtin()),
+        current_usage: Arc::new(Mutex::new(vec![0.0, 0.0])),
+        session: Rc::new(RefCell::new(Session::load(&args.work_dir))),
+        rotation: Rc::new(RefCell::new(None)),
+        work_target_lock: Rc::new(Mutex::new(())),
+        usage_reporter: Rc::new(RefCell::new(None)),
+        counters: agreement.counters.clone(),
+        tera_hash_pos,
+        runtime_config: Rc::new(runtime_config),
     };
 
-    let current_usage = Arc::new(Mutex::new(vec![0.0, 0.0]));
-
     {
-        let batch = ctx.batches.clone();
-        let batch_results = batch.clone();
-
-        let ctx = ctx.clone();
+        let exec_ctx = ctx.clone();
         gsb::bind(&exe_unit_url, move |exec: activity::Exec| {
-            let current_usage = current_usage.clone();
             let exec = exec.clone();
-            let batch = batch.clone();
             let batch_id = exec.batch_id.clone();
-            let batch_id_ = exec.batch_id.clone();
 
-            {
-                let _ = ctx
-                    .batches
-                    .borrow_mut()
-                    .insert(exec.batch_id.clone(), vec![]);
-            }
-            let ctx = ctx.clone();
-            let script_future =
-                prepare_script_future(ctx.clone(), exec, current_usage.clone(), tera_hash_pos)
-                    .map_err(move |e| {
-                        log::error!("ExeScript failure: {e:?}");
-                        let mut bind_batch = batch.borrow_mut();
-                        let result = bind_batch.entry(batch_id_).or_default();
-
-                        let index = result.len() as u32;
-                        result.push(ExeScriptCommandResult {
-                            index,
-                            result: CommandResult::Error,
-                            stdout: None,
-                            stderr: None,
-                            message: Some(e.to_string()),
-                            is_batch_finished: true,
-                            event_date: Utc::now(),
-                        });
-                    });
+            exec_ctx.batches.borrow_mut().start(&batch_id);
+            exec_ctx.events.start(&batch_id);
+            let ctx = exec_ctx.clone();
+            let finished_batch_id = batch_id.clone();
+            let script_future = prepare_script_future(ctx.clone(), exec)
+                .map(move |_| ctx.events.finish(&finished_batch_id));
             tokio::task::spawn_local(script_future);
             future::ok(batch_id)
         });
 
+        let batch_results = ctx.batches.clone();
         gsb::bind(&exe_unit_url, move |exec: activity::GetExecBatchResults| {
-            if let Some(result) = batch_results.borrow().get(&exec.batch_id) {
-                future::ok(result.clone())
-            } else {
-                future::err(RpcMessageError::NotFound(format!(
-                    "Batch id={}",
-                    exec.batch_id
-                )))
+            future::ready(batch_results.borrow_mut().get(&exec.batch_id))
+        });
+
+        let state = ctx.state.clone();
+        gsb::bind(&exe_unit_url, move |_: activity::GetState| {
+            future::ok(state.borrow().clone())
+        });
+
+        let current_usage = ctx.current_usage.clone();
+        gsb::bind(&exe_unit_url, move |_: activity::GetUsage| {
+            let current_usage = current_usage.clone();
+            async move {
+                Ok(ActivityUsage {
+                    current_usage: Some(current_usage.lock().await.clone()),
+                    timestamp: Utc::now().timestamp(),
+                })
             }
         });
+
+        let running_commands = ctx.running_commands.clone();
+        gsb::bind(&exe_unit_url, move |_: activity::GetRunningCommand| {
+            future::ok(running_commands.borrow().values().cloned().collect())
+        });
+
+        let stream_ctx = ctx.clone();
+        gsb::bind_stream(
+            &exe_unit_url,
+            move |msg: activity::StreamExecBatchResults| {
+                if let Some(events) = stream_ctx.events.subscribe(&msg.batch_id) {
+                    events.map(Ok).boxed_local()
+                } else if stream_ctx.batches.borrow().contains(&msg.batch_id) {
+                    // Batch already finished, there is nothing to stream
+                    stream::empty().boxed_local()
+                } else {
+                    stream::once(future::err(RpcMessageError::NotFound(format!(
+                        "Batch id={}",
+                        msg.batch_id
+                    ))))
+                    .boxed_local()
+                }
+            },
+        );
     };
+    spawn_session_restore(&ctx);
+    if args.session_check_interval_sec > 0 {
+        spawn_session_monitor(
+            ctx.clone(),
+            Duration::from_secs(args.session_check_interval_sec),
+        );
+    }
+    if args.health_check_interval_sec > 0 {
+        spawn_health_watchdog(
+            ctx.clone(),
+            WatchdogConfig {
+                interval: Duration::from_secs(args.health_check_interval_sec),
+                max_failures: args.health_max_failures,
+                terminate_after: args.health_terminate_after_sec.map(Duration::from_secs),
+            },
+        );
+    }
     //note that we are here immediately after the bind to gsb
     send_state(
         &ctx,
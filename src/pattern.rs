@@ -0,0 +1,271 @@
+//! Vanity patterns deciding which crunched addresses count as hits
+//!
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::CruncherError;
+
+/// Number of hex characters of an address
+const ADDRESS_NIBBLES: u32 = 40;
+/// Score of the zero address
+const MAX_SCORE: u32 = 2 * ADDRESS_NIBBLES;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CharMask {
+    /// Only `a-f` characters
+    Letters,
+    /// Only `0-9` characters
+    Digits,
+}
+
+impl CharMask {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            CharMask::Letters => c.is_ascii_alphabetic(),
+            CharMask::Digits => c.is_ascii_digit(),
+        }
+    }
+
+    /// Probability that random hex character matches the mask
+    fn probability(&self) -> f64 {
+        match self {
+            CharMask::Letters => 6.0 / 16.0,
+            CharMask::Digits => 10.0 / 16.0,
+        }
+    }
+}
+
+/// Rules of a hit. All given rules have to be satisfied.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pattern {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
+    /// Minimal number of leading zero characters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leading_zeros: Option<u32>,
+    /// Characters allowed in the whole address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mask: Option<CharMask>,
+    /// Minimal score of the address. Every zero character scores 1, and zero characters
+    /// leading the address score 2
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_score: Option<u32>,
+}
+
+impl Pattern {
+    /// Parses `<rule> <value>` pairs, e.g. `prefix 0xdead leading_zeros 4`.
+    pub fn parse(args: &[String]) -> Result<Self, CruncherError> {
+        let mut pattern = Pattern::default();
+        let mut args = args.iter();
+        while let Some(rule) = args.next() {
+            let value = args.next().ok_or_else(|| {
+                CruncherError::InvalidArgument(format!("Missing value of pattern rule {rule}"))
+            })?;
+            match rule.as_str() {
+                "prefix" => pattern.prefix = Some(value.clone()),
+                "suffix" => pattern.suffix = Some(value.clone()),
+                "leading_zeros" => pattern.leading_zeros = Some(parse_count(rule, value)?),
+                "mask" => {
+                    pattern.mask = Some(match value.as_str() {
+                        "letters" => CharMask::Letters,
+                        "digits" => CharMask::Digits,
+                        _ => {
+                            return Err(CruncherError::InvalidArgument(format!(
+                                "Unknown pattern mask {value}, expected letters or digits"
+                            )))
+                        }
+                    })
+                }
+                "min_score" => pattern.min_score = Some(parse_count(rule, value)?),
+                _ => {
+                    return Err(CruncherError::InvalidArgument(format!(
+                        "Unknown pattern rule {rule}"
+                    )))
+                }
+            }
+        }
+        Ok(pattern)
+    }
+
+    /// Checks that rules are well formed and can be satisfied together.
+    /// Returns pattern with normalized prefix and suffix.
+    pub fn validate(self) -> Result<Self, CruncherError> {
+        let prefix = self.prefix.as_deref().map(normalize_hex).transpose()?;
+        let suffix = self.suffix.as_deref().map(normalize_hex).transpose()?;
+        let pattern = Pattern {
+            prefix,
+            suffix,
+            ..self
+        };
+        if pattern == Pattern::default() {
+            return Err(CruncherError::InvalidArgument(
+                "Pattern needs at least one rule".to_string(),
+            ));
+        }
+
+        let prefix = pattern.prefix.as_deref().unwrap_or_default();
+        let suffix = pattern.suffix.as_deref().unwrap_or_default();
+        let leading_zeros = pattern.leading_zeros.unwrap_or(0);
+        if (prefix.len() + suffix.len()) as u32 > ADDRESS_NIBBLES {
+            return Err(CruncherError::InvalidArgument(
+                "Pattern prefix and suffix longer than address".to_string(),
+            ));
+        }
+        if leading_zeros > ADDRESS_NIBBLES || pattern.min_score.unwrap_or(0) > MAX_SCORE {
+            return Err(CruncherError::InvalidArgument(
+                "Pattern requires more characters than address has".to_string(),
+            ));
+        }
+        if prefix
+            .chars()
+            .take(leading_zeros as usize)
+            .any(|c| c != '0')
+        {
+            return Err(CruncherError::InvalidArgument(
+                "Pattern prefix conflicts with leading zeros".to_string(),
+            ));
+        }
+        if let Some(mask) = pattern.mask {
+            let leading_zeros_allowed = leading_zeros == 0 || mask.matches('0');
+            if !leading_zeros_allowed
+                || !prefix
+                    .chars()
+                    .chain(suffix.chars())
+                    .all(|c| mask.matches(c))
+            {
+                return Err(CruncherError::InvalidArgument(
+                    "Pattern conflicts with mask".to_string(),
+                ));
+            }
+        }
+        Ok(pattern)
+    }
+
+    /// Approximate number of addresses which have to be crunched to find a hit.
+    /// Rules are assumed to be independent.
+    pub fn difficulty(&self) -> f64 {
+        let prefix_len = self.prefix.as_ref().map(String::len).unwrap_or(0) as u32;
+        let suffix_len = self.suffix.as_ref().map(String::len).unwrap_or(0) as u32;
+        let fixed_leading = prefix_len.max(self.leading_zeros.unwrap_or(0));
+        let fixed = (fixed_leading + suffix_len).min(ADDRESS_NIBBLES);
+
+        let mut probability = 16f64.powi(-(fixed as i32));
+        if let Some(mask) = self.mask {
+            probability *= mask.probability().powi((ADDRESS_NIBBLES - fixed) as i32);
+        }
+        if let Some(min_score) = self.min_score {
+            probability *= score_probability(min_score, ADDRESS_NIBBLES);
+        }
+        1.0 / probability
+    }
+}
+
+fn parse_count(rule: &str, value: &str) -> Result<u32, CruncherError> {
+    value.parse::<u32>().map_err(|_| {
+        CruncherError::InvalidArgument(format!("Pattern rule {rule} expects a number"))
+    })
+}
+
+fn normalize_hex(value: &str) -> Result<String, CruncherError> {
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    if !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(CruncherError::InvalidArgument(format!(
+            "Pattern {value} is not hex"
+        )));
+    }
+    Ok(value.to_ascii_lowercase())
+}
+
+/// Probability that random address of `nibbles` characters has score of at least
+/// `min_score`. Every zero character scores 1, and zero characters leading
+/// the address score 2, so the zero address has [`MAX_SCORE`].
+fn score_probability(min_score: u32, nibbles: u32) -> f64 {
+    let p_zero: f64 = 1.0 / 16.0;
+    let scores = 2 * nibbles as usize + 1;
+    // Probabilities of scores of addresses with only zeros so far, and of the other ones
+    let mut leading = vec![0.0; scores];
+    let mut other = vec![0.0; scores];
+    leading[0] = 1.0;
+    for _ in 0..nibbles {
+        let mut next_leading = vec![0.0; scores];
+        let mut next_other = vec![0.0; scores];
+        for score in 0..scores {
+            if leading[score] > 0.0 {
+                next_leading[score + 2] += leading[score] * p_zero;
+                next_other[score] += leading[score] * (1.0 - p_zero);
+            }
+            if other[score] > 0.0 {
+                next_other[score + 1] += other[score] * p_zero;
+                next_other[score] += other[score] * (1.0 - p_zero);
+            }
+        }
+        leading = next_leading;
+        other = next_other;
+    }
+    (min_score as usize..scores)
+        .map(|score| leading[score] + other[score])
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(args: &[&str]) -> Result<Pattern, CruncherError> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Pattern::parse(&args)?.validate()
+    }
+
+    #[test]
+    fn test_pattern_difficulty() {
+        let prefix = pattern(&["prefix", "0xDEAD"]).unwrap();
+        assert_eq!(prefix.prefix.as_deref(), Some("dead"));
+        assert_eq!(prefix.difficulty(), 65536.0);
+
+        let zeros = pattern(&["prefix", "00", "leading_zeros", "4", "suffix", "f"]).unwrap();
+        assert_eq!(zeros.difficulty(), 16f64.powi(5));
+
+        let score = pattern(&["min_score", "0"]).unwrap();
+        assert!((score.difficulty() - 1.0).abs() < 1e-9);
+        let score = pattern(&["min_score", "80"]).unwrap();
+        assert_eq!(score.difficulty(), 16f64.powi(40));
+        assert!(pattern(&["min_score", "81"]).is_err());
+    }
+
+    #[test]
+    fn test_score_probability() {
+        let score = |address: &[u32]| {
+            let leading = address.iter().take_while(|c| **c == 0).count();
+            let zeros = address.iter().filter(|c| **c == 0).count();
+            (leading + zeros) as u32
+        };
+        let addresses: Vec<[u32; 3]> = (0..16u32.pow(3))
+            .map(|n| [n / 256, n / 16 % 16, n % 16])
+            .collect();
+        for min_score in 0..=7 {
+            let hits = addresses
+                .iter()
+                .filter(|address| score(address.as_slice()) >= min_score)
+                .count();
+            let expected = hits as f64 / addresses.len() as f64;
+            assert!(
+                (score_probability(min_score, 3) - expected).abs() < 1e-12,
+                "min_score {min_score}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_reject_conflicting_pattern() {
+        assert!(pattern(&[]).is_err());
+        assert!(pattern(&["prefix", "xyz"]).is_err());
+        assert!(pattern(&["prefix", "1", "leading_zeros", "2"]).is_err());
+        assert!(pattern(&["mask", "letters", "leading_zeros", "1"]).is_err());
+        assert!(pattern(&["mask", "digits", "suffix", "a"]).is_err());
+        assert!(pattern(&["leading_zeros"]).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+//! Provider policy restricting work targets and patterns
+//!
+
+use serde::Deserialize;
+
+use crate::error::CruncherError;
+use crate::pattern::Pattern;
+use crate::requests::WorkTarget;
+use crate::work_target::{parse_address, parse_public_key};
+
+/// `policy` section of the runtime config. Empty section allows everything.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Policy {
+    /// Factory and `CREATE2` deployer addresses allowed to be crunched. All are allowed if not set
+    pub allowed_factories: Option<Vec<String>>,
+    /// Public key bases which are never crunched
+    pub denied_keys: Vec<String>,
+    /// Whether client API default work target can be used
+    pub allow_default: bool,
+    /// Maximum estimated number of attempts of a pattern
+    pub max_pattern_difficulty: Option<f64>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            allowed_factories: None,
+            denied_keys: Vec::new(),
+            allow_default: true,
+            max_pattern_difficulty: None,
+        }
+    }
+}
+
+impl Policy {
+    /// Validates policy entries and normalizes them into the sanitized work target encoding.
+    pub fn normalize(self) -> Result<Self, CruncherError> {
+        let allowed_factories = self
+            .allowed_factories
+            .map(|factories| {
+                factories
+                    .iter()
+                    .map(|factory| {
+                        parse_address(factory, "factory")
+                            .map(|address| format!("0x{}", hex::encode(address)))
+                    })
+                    .collect::<Result<Vec<_>, CruncherError>>()
+            })
+            .transpose()?;
+        let denied_keys = self
+            .denied_keys
+            .iter()
+            .map(|key| parse_public_key(key).map(|key| format!("0x{}", hex::encode(key))))
+            .collect::<Result<Vec<_>, CruncherError>>()?;
+        Ok(Self {
+            allowed_factories,
+            denied_keys,
+            ..self
+        })
+    }
+
+    /// Checks sanitized work target against the policy.
+    pub fn check_work_target(&self, work_target: &WorkTarget) -> Result<(), CruncherError> {
+        match work_target {
+            WorkTarget::Factory(address)
+            | WorkTarget::Create2 {
+                deployer: address, ..
+            } => {
+                if let Some(allowed) = &self.allowed_factories {
+                    if !allowed.contains(address) {
+                        return Err(CruncherError::PolicyViolation(format!(
+                            "{work_target} is not allowed by provider"
+                        )));
+                    }
+                }
+            }
+            WorkTarget::PublicKeyBase(key) => {
+                if self.denied_keys.contains(key) {
+                    return Err(CruncherError::PolicyViolation(format!(
+                        "{work_target} is denied by provider"
+                    )));
+                }
+            }
+            WorkTarget::Default => {
+                if !self.allow_default {
+                    return Err(CruncherError::PolicyViolation(
+                        "Default work target is not allowed by provider".to_string(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks work target runners would crunch. Without work target set by the runtime
+    /// runners use the client API default one.
+    pub fn check_runners_target(
+        &self,
+        work_target: Option<&WorkTarget>,
+    ) -> Result<(), CruncherError> {
+        self.check_work_target(work_target.unwrap_or(&WorkTarget::Default))
+    }
+
+    /// Checks validated pattern against maximum difficulty.
+    pub fn check_pattern(&self, pattern: &Pattern) -> Result<(), CruncherError> {
+        let difficulty = pattern.difficulty();
+        match self.max_pattern_difficulty {
+            Some(max) if difficulty > max => Err(CruncherError::PolicyViolation(format!(
+                "Pattern difficulty {difficulty:e} exceeds provider limit {max:e}"
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::work_target::sanitize_work_target;
+
+    #[test]
+    fn test_policy_work_target() {
+        let policy: Policy = serde_json::from_value(serde_json::json!({
+            "allowedFactories": ["0x9E3F1B2A4C5D6E7F8091A2B3C4D5E6F708192A3B"],
+            "allowDefault": false,
+        }))
+        .unwrap();
+        let policy = policy.normalize().unwrap();
+
+        let allowed = sanitize_work_target(WorkTarget::Factory(
+            "0x9e3f1b2a4c5d6e7f8091a2b3c4d5e6f708192a3b".to_string(),
+        ))
+        .unwrap();
+        assert_eq!(policy.check_work_target(&allowed), Ok(()));
+
+        let other = WorkTarget::Factory("0x0000000000000000000000000000000000000001".to_string());
+        assert!(matches!(
+            policy.check_work_target(&other),
+            Err(CruncherError::PolicyViolation(_))
+        ));
+        assert!(matches!(
+            policy.check_work_target(&WorkTarget::Default),
+            Err(CruncherError::PolicyViolation(_))
+        ));
+        assert_eq!(policy.check_runners_target(Some(&allowed)), Ok(()));
+        assert!(matches!(
+            policy.check_runners_target(None),
+            Err(CruncherError::PolicyViolation(_))
+        ));
+        assert_eq!(Policy::default().check_runners_target(None), Ok(()));
+    }
+
+    #[test]
+    fn test_policy_denied_key() {
+        // Generator point of secp256k1, denied in compressed form
+        let g_x = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let g_y = "483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+        let policy: Policy = serde_json::from_value(serde_json::json!({
+            "deniedKeys": [format!("02{}", g_x.to_uppercase())],
+        }))
+        .unwrap();
+        let policy = policy.normalize().unwrap();
+
+        let denied =
+            sanitize_work_target(WorkTarget::PublicKeyBase(format!("0x{g_x}{g_y}"))).unwrap();
+        assert!(matches!(
+            policy.check_work_target(&denied),
+            Err(CruncherError::PolicyViolation(_))
+        ));
+
+        let invalid: Policy =
+            serde_json::from_value(serde_json::json!({ "deniedKeys": ["0x1234"] })).unwrap();
+        assert!(matches!(
+            invalid.normalize(),
+            Err(CruncherError::InvalidArgument(_))
+        ));
+    }
+}
@@ -0,0 +1,97 @@
+//! Progress of running commands, polled from client API and emitted as runtime events
+//!
+
+use std::future::Future;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use ya_client_model::activity::{CommandOutput, RuntimeEvent};
+
+use crate::error::CruncherError;
+use crate::events::BatchEvents;
+use crate::requests::RunnersResponse;
+
+/// Interval of progress lines emitted while a command reporting progress runs
+pub const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Every `interval` polls runners status with `poll` and emits it as a stdout line,
+/// or a stderr line when client API does not respond, until the returned handle is aborted.
+pub fn spawn_progress_emitter<F, Fut>(
+    events: BatchEvents,
+    batch_id: String,
+    index: usize,
+    interval: Duration,
+    poll: F,
+) -> JoinHandle<()>
+where
+    F: Fn() -> Fut + 'static,
+    Fut: Future<Output = Result<RunnersResponse, CruncherError>>,
+{
+    tokio::task::spawn_local(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            let event = match poll().await {
+                Ok(runners) => RuntimeEvent::stdout(
+                    batch_id.clone(),
+                    index,
+                    CommandOutput::Str(format!("{}\n", runners.to_stdout())),
+                ),
+                Err(e) => RuntimeEvent::stderr(
+                    batch_id.clone(),
+                    index,
+                    CommandOutput::Str(format!("Failed to get runners status: {e}\n")),
+                ),
+            };
+            events.emit(event);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use ya_client_model::activity::RuntimeEventKind;
+
+    #[actix_rt::test]
+    async fn test_progress_received_by_subscriber() {
+        let events = BatchEvents::default();
+        events.start("batch");
+        let mut stream = events.subscribe("batch").unwrap();
+        let body = r#"{"runners":[{"id":0,"status":"started"}]}"#;
+        let polls = Rc::new(Cell::new(0));
+
+        let emitter = spawn_progress_emitter(
+            events.clone(),
+            "batch".to_string(),
+            2,
+            Duration::from_millis(10),
+            {
+                let polls = polls.clone();
+                move || {
+                    polls.set(polls.get() + 1);
+                    let response = match polls.get() {
+                        1 => Ok(RunnersResponse::parse(body.to_string())),
+                        _ => Err(CruncherError::BackendUnreachable("down".to_string())),
+                    };
+                    async move { response }
+                }
+            },
+        );
+        let status = stream.next().await.unwrap();
+        let failure = stream.next().await.unwrap();
+        emitter.abort();
+
+        assert_eq!(status.batch_id, "batch");
+        assert_eq!(status.index, 2);
+        let RuntimeEventKind::StdOut(CommandOutput::Str(line)) = status.kind else {
+            panic!("Expected stdout line, got {:?}", status.kind);
+        };
+        assert_eq!(line, format!("{body}\n"));
+        let RuntimeEventKind::StdErr(CommandOutput::Str(line)) = failure.kind else {
+            panic!("Expected stderr line, got {:?}", failure.kind);
+        };
+        assert!(line.contains("down"), "{line}");
+    }
+}
@@ -1,137 +1,957 @@
 use anyhow::anyhow;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::path::PathBuf;
 use std::sync::OnceLock;
-use ya_core_model::activity::RpcMessageError;
+use std::time::Duration;
 
-static CLIENT_API_URL: OnceLock<String> = OnceLock::new();
+use crate::auth::ClientApiAuth;
+use crate::error::CruncherError;
+use crate::pattern::Pattern;
 
-pub fn init_client_api_url() -> Result<&'static String, anyhow::Error> {
+static CLIENT_API_URL: OnceLock<ClientApiUrl> = OnceLock::new();
+static HTTP_CLIENT: OnceLock<HttpClient> = OnceLock::new();
+
+const UNIX_SOCKET_SCHEME: &str = "unix://";
+/// Base of request URLs sent over unix socket. Host is not resolved.
+const UNIX_SOCKET_BASE_URL: &str = "http://localhost";
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(10);
+/// Endpoint requested by `Deploy` to check that client API is ready
+const PROBE_PATH: &str = "/";
+
+/// Retries of client API requests failed with connection errors or 5xx responses
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    pub max_retries: u32,
+    /// Backoff before the first retry, doubled with each next one
+    pub initial_backoff: Duration,
+}
+
+impl RetryConfig {
+    /// Exponential backoff with jitter, between half and full delay of given retry.
+    fn backoff(&self, retry: u32) -> Duration {
+        let delay = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(MAX_RETRY_BACKOFF);
+        delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+/// Settings of HTTP client used for client API requests
+#[derive(Debug, Clone)]
+pub struct HttpClientConfig {
+    pub connect_timeout: Duration,
+    pub timeout: Duration,
+    pub retry: RetryConfig,
+    pub auth: Option<ClientApiAuth>,
+}
+
+/// Client API location given either as `http(s)://host:port` or `unix:///path/to.sock`
+#[derive(Debug, Clone, PartialEq)]
+struct ClientApiUrl {
+    /// Base of request URLs
+    base_url: String,
+    /// Socket requests are sent to instead of TCP connection to `base_url` host
+    unix_socket: Option<PathBuf>,
+    /// URL as configured, shown in logs and errors
+    display: String,
+}
+
+impl ClientApiUrl {
+    fn parse(client_api_url: &str) -> Self {
+        match client_api_url.strip_prefix(UNIX_SOCKET_SCHEME) {
+            Some(socket) => ClientApiUrl {
+                base_url: UNIX_SOCKET_BASE_URL.to_string(),
+                unix_socket: Some(PathBuf::from(socket)),
+                display: client_api_url.to_string(),
+            },
+            None => ClientApiUrl {
+                base_url: client_api_url.to_string(),
+                unix_socket: None,
+                display: client_api_url.to_string(),
+            },
+        }
+    }
+
+    /// URL of request to `path` for logs and errors.
+    fn display_url(&self, path: &str) -> String {
+        match self.unix_socket {
+            Some(_) => format!("{path} via {}", self.display),
+            None => format!("{}{path}", self.display),
+        }
+    }
+}
+
+pub fn init_client_api_url() -> Result<(), anyhow::Error> {
     let client_api_url = env::var("CRUNCHER_CLIENT_API_URL")
         .map_err(|e|anyhow!("CRUNCHER_CLIENT_API_URL not set: {e}. Without this variable runtime cannot connect to client API"))?;
     CLIENT_API_URL
-        .set(client_api_url)
+        .set(ClientApiUrl::parse(&client_api_url))
         .expect("CLIENT_API_URL can be set only once");
-    let client_api_url = get_client_api_url();
-    log::info!("Client API URL set to {}", *client_api_url);
-    Ok(client_api_url)
+    log::info!("Client API URL set to {}", client_api_url);
+    Ok(())
 }
 
-pub fn get_client_api_url() -> &'static String {
-    CLIENT_API_URL
+/// Builds HTTP client with connection pool shared by all client API requests.
+pub fn init_http_client(config: HttpClientConfig) -> anyhow::Result<()> {
+    let url = CLIENT_API_URL
         .get()
         .expect("CLIENT_API_URL not initialized")
+        .clone();
+    let http_client = HttpClient::new(url, config)?;
+    HTTP_CLIENT
+        .set(http_client)
+        .expect("HTTP_CLIENT can be set only once");
+    Ok(())
+}
+
+fn get_http_client() -> &'static HttpClient {
+    HTTP_CLIENT.get().expect("HTTP_CLIENT not initialized")
+}
+
+#[derive(Debug)]
+struct HttpClient {
+    client: reqwest::Client,
+    url: ClientApiUrl,
+    retry: RetryConfig,
+    auth: Option<ClientApiAuth>,
+}
+
+impl HttpClient {
+    fn new(url: ClientApiUrl, config: HttpClientConfig) -> anyhow::Result<Self> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.timeout)
+            .user_agent(USER_AGENT);
+        if let Some(auth) = &config.auth {
+            builder = builder.default_headers(auth.headers()?);
+        }
+        let builder = match &url.unix_socket {
+            #[cfg(unix)]
+            Some(socket) => builder.unix_socket(socket.clone()),
+            #[cfg(not(unix))]
+            Some(_) => anyhow::bail!("Client API unix socket is supported only on unix systems"),
+            None => builder,
+        };
+        let client = builder
+            .build()
+            .map_err(|e| anyhow!("Failed to build client API HTTP client: {e}"))?;
+        log::info!(
+            "Client API timeouts set to connect={}s, request={}s, retries={}, auth={:?}",
+            config.connect_timeout.as_secs(),
+            config.timeout.as_secs(),
+            config.retry.max_retries,
+            config.auth
+        );
+        Ok(Self {
+            client,
+            url,
+            retry: config.retry,
+            auth: config.auth,
+        })
+    }
+
+    /// Sends request authenticated with configured credentials.
+    async fn execute(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let mut request = request.build()?;
+        if let Some(auth) = &self.auth {
+            auth.sign(&mut request);
+        }
+        self.client.execute(request).await
+    }
+
+    /// Error message with URL of the request as configured, not as sent over unix socket.
+    fn describe(&self, e: &reqwest::Error) -> String {
+        let message = e.to_string();
+        match (e.url(), &self.url.unix_socket) {
+            (Some(url), Some(_)) => {
+                message.replace(url.as_str(), &self.url.display_url(url.path()))
+            }
+            _ => message,
+        }
+    }
+
+    /// Error of request which could not be sent.
+    fn send_error(&self, e: reqwest::Error) -> CruncherError {
+        let e = self.describe(&e);
+        log::error!("Failed to send request: {}", e);
+        CruncherError::BackendUnreachable(format!("Failed to send request {e}"))
+    }
+
+    /// Sends request built by `build`, retrying on connection errors and 5xx responses.
+    /// All attempts carry the same idempotency key, so backend can ignore repeated ones.
+    async fn send_request(
+        &self,
+        build: impl Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let idempotency_key = hex::encode(rand::random::<[u8; 16]>());
+        let mut retry = 0;
+        loop {
+            let result = self
+                .execute(build(&self.client).header(IDEMPOTENCY_KEY_HEADER, &idempotency_key))
+                .await;
+            let failure = match &result {
+                Ok(res) if res.status().is_server_error() => res.status().to_string(),
+                Ok(_) => return result,
+                Err(e) => self.describe(e),
+            };
+            if retry >= self.retry.max_retries {
+                return result;
+            }
+            let backoff = self.retry.backoff(retry);
+            retry += 1;
+            log::warn!(
+                "Client API request failed: {failure}. Retry {retry}/{} in {}ms",
+                self.retry.max_retries,
+                backoff.as_millis()
+            );
+            tokio::time::sleep(backoff).await;
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum WorkTarget {
     Factory(String),
     PublicKeyBase(String),
+    /// Contract address created with `CREATE2` by `deployer`
+    #[serde(rename_all = "camelCase")]
+    Create2 {
+        deployer: String,
+        init_code_hash: String,
+        /// Fixed leading bytes of the salt, e.g. requestor address
+        salt_prefix: Option<String>,
+    },
     Default,
 }
 
-// Async function to post WorkTarget
-pub async fn send_work_target(target: WorkTarget) -> Result<(), RpcMessageError> {
-    let client = reqwest::Client::new();
-    let api_base = get_client_api_url();
-
-    let target_url = format!("{api_base}/api/runners/target/set");
-    let res = client
-        .post(&target_url) // Replace with your actual endpoint
-        .json(&target)
-        .send()
-        .await
-        .map_err(|e| {
-            log::error!("Failed to send request: {}", e);
-            RpcMessageError::Activity(format!("Failed to send request {e}"))
-        })?;
-
-    if res.status().is_success() {
-        log::info!("Successfully set WorkTarget {:?}", target);
-        Ok(())
-    } else {
-        let status = res.status();
-        log::error!("Failed to set WorkTarget: {} - url: {}", status, target_url);
-        let text = if let Ok(text) = res.text().await {
-            log::error!("Response: {}", text);
-            text
-        } else {
-            "".to_string()
-        };
-        Err(RpcMessageError::Activity(format!(
-            "Failed to set WorkTarget: {} {}",
-            status, text
-        )))
+/// Body of `/api/runners/target/set`. Without pattern it is the bare work target, e.g.
+/// `{"factory": "0x..."}`. Pattern of hits is sent in the same request together with
+/// the work target, e.g. `{"target": {"factory": "0x..."}, "pattern": {"prefix": "dead"}}`.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum TargetRequest<'a> {
+    Target(&'a WorkTarget),
+    WithPattern {
+        target: &'a WorkTarget,
+        pattern: &'a Pattern,
+    },
+}
+
+/// Identifier of a runner, index or name depending on backend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RunnerId {
+    Index(u64),
+    Name(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RunnerStatus {
+    pub id: RunnerId,
+    /// Status reported by backend, e.g. `started` or `failed`
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RunnersStatus {
+    pub runners: Vec<RunnerStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// [`RunnerStatus::status`] of a running runner
+pub const RUNNER_STARTED: &str = "started";
+
+/// Response of runners start, stop and status. Bodies of unknown shape are kept as received.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunnersResponse {
+    Runners(RunnersStatus),
+    Raw(String),
+}
+
+impl RunnersResponse {
+    pub fn parse(body: String) -> Self {
+        match serde_json::from_str(&body) {
+            Ok(runners) => RunnersResponse::Runners(runners),
+            Err(_) => RunnersResponse::Raw(body),
+        }
+    }
+
+    /// Command stdout: runners status as JSON or unchanged response body.
+    pub fn to_stdout(&self) -> String {
+        match self {
+            RunnersResponse::Runners(runners) => serde_json::to_string(runners).unwrap_or_default(),
+            RunnersResponse::Raw(body) => body.clone(),
+        }
     }
 }
 
-// Async function to post WorkTarget
-pub async fn start_work() -> Result<(), RpcMessageError> {
-    let client = reqwest::Client::new();
-    let api_base = get_client_api_url();
-
-    let target_url = format!("{api_base}/api/runners/start");
-    let res = client
-        .post(&target_url) // Replace with your actual endpoint
-        .send()
-        .await
-        .map_err(|e| {
-            log::error!("Failed to send request: {}", e);
-            RpcMessageError::Activity(format!("Failed to send request {e}"))
-        })?;
-
-    if res.status().is_success() {
-        let message = res.text().await.unwrap_or("".to_string());
-        log::info!("Successfully started runners with message: {}", message);
-        Ok(())
-    } else {
-        let status = res.status();
-        log::error!("Failed to start runners: {} - url: {}", status, target_url);
-        let text = if let Ok(text) = res.text().await {
-            log::error!("Response: {}", text);
-            text
-        } else {
-            "".to_string()
+impl HttpClient {
+    async fn send_work_target(
+        &self,
+        target: WorkTarget,
+        pattern: Option<&Pattern>,
+    ) -> Result<(), CruncherError> {
+        let api_base = &self.url.base_url;
+
+        let request_url = format!("{api_base}/api/runners/target/set");
+        let target_url = self.url.display_url("/api/runners/target/set");
+        let body = match pattern {
+            Some(pattern) => TargetRequest::WithPattern {
+                target: &target,
+                pattern,
+            },
+            None => TargetRequest::Target(&target),
         };
-        Err(RpcMessageError::Activity(format!(
-            "Failed to start runners: {} {}",
-            status, text
-        )))
+        let res = self
+            .send_request(|client| client.post(&request_url).json(&body))
+            .await
+            .map_err(|e| self.send_error(e))?;
+
+        if res.status().is_success() {
+            log::info!(
+                "Successfully set WorkTarget {} with pattern {:?}",
+                target,
+                pattern
+            );
+            Ok(())
+        } else {
+            let status = res.status();
+            log::error!("Failed to set WorkTarget: {} - url: {}", status, target_url);
+            let text = if let Ok(text) = res.text().await {
+                log::error!("Response: {}", text);
+                text
+            } else {
+                "".to_string()
+            };
+            Err(CruncherError::BackendRejected(format!(
+                "Failed to set WorkTarget: {} {}",
+                status, text
+            )))
+        }
+    }
+
+    async fn start_work(&self) -> Result<RunnersResponse, CruncherError> {
+        let api_base = &self.url.base_url;
+
+        let request_url = format!("{api_base}/api/runners/start");
+        let target_url = self.url.display_url("/api/runners/start");
+        let res = self
+            .send_request(|client| client.post(&request_url))
+            .await
+            .map_err(|e| self.send_error(e))?;
+
+        if res.status().is_success() {
+            let message = res.text().await.unwrap_or("".to_string());
+            log::info!("Successfully started runners with message: {}", message);
+            Ok(RunnersResponse::parse(message))
+        } else {
+            let status = res.status();
+            log::error!("Failed to start runners: {} - url: {}", status, target_url);
+            let text = if let Ok(text) = res.text().await {
+                log::error!("Response: {}", text);
+                text
+            } else {
+                "".to_string()
+            };
+            Err(CruncherError::BackendRejected(format!(
+                "Failed to start runners: {} {}",
+                status, text
+            )))
+        }
+    }
+
+    async fn stop_work(&self) -> Result<RunnersResponse, CruncherError> {
+        let api_base = &self.url.base_url;
+
+        let request_url = format!("{api_base}/api/runners/stop");
+        let target_url = self.url.display_url("/api/runners/stop");
+        let res = self
+            .send_request(|client| client.post(&request_url))
+            .await
+            .map_err(|e| self.send_error(e))?;
+
+        if res.status().is_success() {
+            let message = res.text().await.unwrap_or("".to_string());
+            log::info!("Successfully stopped runners with message: {}", message);
+            Ok(RunnersResponse::parse(message))
+        } else {
+            let status = res.status();
+            log::error!("Failed to stop runners: {} - url: {}", status, target_url);
+            let text = if let Ok(text) = res.text().await {
+                log::error!("Response: {}", text);
+                text
+            } else {
+                "".to_string()
+            };
+            Err(CruncherError::BackendRejected(format!(
+                "Failed to stop runners: {} {}",
+                status, text
+            )))
+        }
+    }
+
+    /// Probes work target endpoint, which runtime relies on, for a successful response.
+    async fn check_client_api(&self) -> Result<(), CruncherError> {
+        let api_base = &self.url.base_url;
+
+        let request_url = format!("{api_base}{PROBE_PATH}");
+        let target_url = self.url.display_url(PROBE_PATH);
+        let res = self
+            .send_request(|client| client.get(&request_url))
+            .await
+            .map_err(|e| {
+                let e = self.describe(&e);
+                log::error!("Client API unreachable: {}", e);
+                CruncherError::BackendUnreachable(format!(
+                    "Client API unreachable at {}: {e}",
+                    self.url.display
+                ))
+            })?;
+
+        // Any response other than server error means client API is up, even if it does not
+        // serve the probed path
+        if res.status().is_server_error() {
+            let status = res.status();
+            log::error!("Client API not ready: {} - url: {}", status, target_url);
+            Err(CruncherError::BackendRejected(format!(
+                "Client API not ready at {}: {status}",
+                self.url.display
+            )))
+        } else {
+            log::info!("Client API reachable at {}", self.url.display);
+            Ok(())
+        }
+    }
+
+    async fn get_work_target(&self) -> Result<serde_json::Value, CruncherError> {
+        let api_base = &self.url.base_url;
+
+        let request_url = format!("{api_base}/api/runners/target");
+        let target_url = self.url.display_url("/api/runners/target");
+        let res = self
+            .send_request(|client| client.get(&request_url))
+            .await
+            .map_err(|e| self.send_error(e))?;
+
+        if res.status().is_success() {
+            res.json().await.map_err(|e| {
+                log::error!("Invalid WorkTarget response: {}", e);
+                CruncherError::BackendRejected(format!("Invalid WorkTarget response {e}"))
+            })
+        } else {
+            let status = res.status();
+            log::error!("Failed to get WorkTarget: {} - url: {}", status, target_url);
+            let text = if let Ok(text) = res.text().await {
+                log::error!("Response: {}", text);
+                text
+            } else {
+                "".to_string()
+            };
+            Err(CruncherError::BackendRejected(format!(
+                "Failed to get WorkTarget: {} {}",
+                status, text
+            )))
+        }
+    }
+
+    async fn get_runners(&self) -> Result<RunnersResponse, CruncherError> {
+        let api_base = &self.url.base_url;
+
+        let request_url = format!("{api_base}/api/runners");
+        let target_url = self.url.display_url("/api/runners");
+        let res = self
+            .send_request(|client| client.get(&request_url))
+            .await
+            .map_err(|e| self.send_error(e))?;
+
+        if res.status().is_success() {
+            Ok(RunnersResponse::parse(res.text().await.unwrap_or_default()))
+        } else {
+            let status = res.status();
+            log::debug!("Failed to get runners: {} - url: {}", status, target_url);
+            let text = res.text().await.unwrap_or_default();
+            Err(CruncherError::BackendRejected(format!(
+                "Failed to get runners: {} {}",
+                status, text
+            )))
+        }
+    }
+
+    async fn check_health(&self) -> Result<(), CruncherError> {
+        let api_base = &self.url.base_url;
+
+        let request_url = format!("{api_base}/api/runners/health");
+        let target_url = self.url.display_url("/api/runners/health");
+        // Failures are counted by the health watchdog, so the request is not retried
+        let res = self
+            .execute(self.client.get(&request_url))
+            .await
+            .map_err(|e| {
+                let e = self.describe(&e);
+                log::debug!("Failed to send health request: {}", e);
+                CruncherError::BackendUnreachable(format!("Failed to send request {e}"))
+            })?;
+
+        if res.status().is_success() {
+            Ok(())
+        } else {
+            let status = res.status();
+            let text = res.text().await.unwrap_or_default();
+            log::debug!(
+                "Runners unhealthy: {} {} - url: {}",
+                status,
+                text,
+                target_url
+            );
+            Err(CruncherError::BackendRejected(format!(
+                "Runners unhealthy: {} {}",
+                status, text
+            )))
+        }
     }
 }
 
+// Async function to post WorkTarget together with pattern of hits
+pub async fn send_work_target(
+    target: WorkTarget,
+    pattern: Option<&Pattern>,
+) -> Result<(), CruncherError> {
+    get_http_client().send_work_target(target, pattern).await
+}
+
 // Async function to post WorkTarget
-pub async fn stop_work() -> Result<(), RpcMessageError> {
-    let client = reqwest::Client::new();
-    let api_base = get_client_api_url();
-
-    let target_url = format!("{api_base}/api/runners/stop");
-    let res = client
-        .post(&target_url) // Replace with your actual endpoint
-        .send()
-        .await
-        .map_err(|e| {
-            log::error!("Failed to send request: {}", e);
-            RpcMessageError::Activity(format!("Failed to send request {e}"))
-        })?;
-
-    if res.status().is_success() {
-        let message = res.text().await.unwrap_or("".to_string());
-        log::info!("Successfully stopped runners with message: {}", message);
-        Ok(())
-    } else {
-        let status = res.status();
-        log::error!("Failed to stop runners: {} - url: {}", status, target_url);
-        let text = if let Ok(text) = res.text().await {
-            log::error!("Response: {}", text);
-            text
-        } else {
-            "".to_string()
+pub async fn start_work() -> Result<RunnersResponse, CruncherError> {
+    get_http_client().start_work().await
+}
+
+// Async function to post WorkTarget
+pub async fn stop_work() -> Result<RunnersResponse, CruncherError> {
+    get_http_client().stop_work().await
+}
+
+// Async function checking that client API responds
+pub async fn check_client_api() -> Result<(), CruncherError> {
+    get_http_client().check_client_api().await
+}
+
+// Async function to get currently active WorkTarget
+pub async fn get_work_target() -> Result<serde_json::Value, CruncherError> {
+    get_http_client().get_work_target().await
+}
+
+// Async function to get status of cruncher runners
+pub async fn get_runners() -> Result<RunnersResponse, CruncherError> {
+    get_http_client().get_runners().await
+}
+
+// Async function checking health of cruncher runners
+pub async fn check_health() -> Result<(), CruncherError> {
+    get_http_client().check_health().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{signature, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    fn http_client(url: &str) -> HttpClient {
+        retrying_client(url, 0)
+    }
+
+    fn retrying_client(url: &str, max_retries: u32) -> HttpClient {
+        authenticated_client(url, max_retries, None)
+    }
+
+    fn authenticated_client(
+        url: &str,
+        max_retries: u32,
+        auth: Option<ClientApiAuth>,
+    ) -> HttpClient {
+        HttpClient::new(
+            ClientApiUrl::parse(url),
+            HttpClientConfig {
+                connect_timeout: Duration::from_secs(1),
+                timeout: Duration::from_secs(5),
+                retry: RetryConfig {
+                    max_retries,
+                    initial_backoff: Duration::from_millis(1),
+                },
+                auth,
+            },
+        )
+        .unwrap()
+    }
+
+    async fn mock_start(server: &MockServer, status: u16, times: u64) {
+        Mock::given(method("POST"))
+            .and(path("/api/runners/start"))
+            .respond_with(ResponseTemplate::new(status))
+            .up_to_n_times(times)
+            .expect(times)
+            .mount(server)
+            .await;
+    }
+
+    #[actix_rt::test]
+    async fn test_retry_server_errors_with_same_idempotency_key() {
+        let server = MockServer::start().await;
+        mock_start(&server, 503, 3).await;
+
+        let result = retrying_client(&server.uri(), 2).start_work().await;
+        assert!(matches!(result, Err(CruncherError::BackendRejected(_))));
+
+        let requests = server.received_requests().await.unwrap();
+        let keys: Vec<_> = requests
+            .iter()
+            .map(|request| request.headers.get(IDEMPOTENCY_KEY_HEADER).cloned())
+            .collect();
+        assert_eq!(keys.len(), 3);
+        assert!(keys[0].as_ref().is_some_and(|key| !key.is_empty()));
+        assert!(keys.iter().all(|key| *key == keys[0]));
+    }
+
+    #[actix_rt::test]
+    async fn test_retry_until_success() {
+        let server = MockServer::start().await;
+        mock_start(&server, 500, 2).await;
+        mock_start(&server, 200, 1).await;
+
+        let result = retrying_client(&server.uri(), 3).start_work().await;
+        assert_eq!(result, Ok(RunnersResponse::Raw(String::new())));
+
+        // Next request gets a new idempotency key
+        mock_start(&server, 200, 1).await;
+        retrying_client(&server.uri(), 3)
+            .start_work()
+            .await
+            .unwrap();
+        let requests = server.received_requests().await.unwrap();
+        let key = |index: usize| requests[index].headers.get(IDEMPOTENCY_KEY_HEADER);
+        assert_eq!(key(0), key(2));
+        assert_ne!(key(2), key(3));
+    }
+
+    #[actix_rt::test]
+    async fn test_client_errors_not_retried() {
+        let server = MockServer::start().await;
+        mock_start(&server, 409, 1).await;
+
+        let result = retrying_client(&server.uri(), 3).start_work().await;
+        assert!(matches!(result, Err(CruncherError::BackendRejected(_))));
+    }
+
+    #[actix_rt::test]
+    async fn test_retry_connection_errors() {
+        // Accepts connections and closes them without response
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let connections = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let accepted = connections.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                accepted.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                drop(stream);
+            }
+        });
+
+        let result = retrying_client(&url, 2).start_work().await;
+        assert!(matches!(result, Err(CruncherError::BackendUnreachable(_))));
+        assert_eq!(connections.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_retry_backoff_bounds() {
+        let retry = RetryConfig {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(100),
+        };
+        for attempt in 0..10 {
+            let delay = (Duration::from_millis(100) * 2u32.pow(attempt)).min(MAX_RETRY_BACKOFF);
+            for _ in 0..20 {
+                let backoff = retry.backoff(attempt);
+                assert!(
+                    backoff >= delay / 2 && backoff <= delay,
+                    "{backoff:?} of retry {attempt}"
+                );
+            }
+        }
+        assert!(retry.backoff(u32::MAX) <= MAX_RETRY_BACKOFF);
+    }
+
+    /// URL of a local port nothing listens on.
+    fn unused_url() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    #[actix_rt::test]
+    async fn test_send_pattern_with_work_target() {
+        let server = MockServer::start().await;
+        let target = WorkTarget::Factory("0x01".to_string());
+        let pattern = Pattern {
+            prefix: Some("dead".to_string()),
+            ..Pattern::default()
+        };
+        Mock::given(method("POST"))
+            .and(path("/api/runners/target/set"))
+            .and(body_json(serde_json::json!({"factory": "0x01"})))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/runners/target/set"))
+            .and(body_json(serde_json::json!({
+                "target": {"factory": "0x01"},
+                "pattern": {"prefix": "dead"},
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = http_client(&server.uri());
+        assert_eq!(client.send_work_target(target.clone(), None).await, Ok(()));
+        assert_eq!(
+            client.send_work_target(target, Some(&pattern)).await,
+            Ok(())
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_check_client_api() {
+        let server = MockServer::start().await;
+        let client = http_client(&server.uri());
+        // Unmatched requests are answered with 404, which still proves API is up
+        assert_eq!(client.check_client_api().await, Ok(()));
+
+        Mock::given(method("GET"))
+            .and(path(PROBE_PATH))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+        assert!(matches!(
+            client.check_client_api().await,
+            Err(CruncherError::BackendRejected(_))
+        ));
+
+        assert!(matches!(
+            http_client(&unused_url()).check_client_api().await,
+            Err(CruncherError::BackendUnreachable(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_runners_response() {
+        let body = r#"{"runners":[{"id":0,"status":"started"},{"id":1,"status":"failed","error":"no device"}]}"#;
+        let response = RunnersResponse::parse(body.to_string());
+        let RunnersResponse::Runners(runners) = &response else {
+            panic!("Expected typed response, got {response:?}");
+        };
+        assert_eq!(runners.runners[1].id, RunnerId::Index(1));
+        assert_eq!(runners.runners[1].error.as_deref(), Some("no device"));
+        assert_eq!(response.to_stdout(), body);
+
+        for body in ["Runners started", r#"{"started":2}"#, ""] {
+            assert_eq!(RunnersResponse::parse(body.to_string()).to_stdout(), body);
+        }
+    }
+
+    #[test]
+    fn test_parse_client_api_url() {
+        assert_eq!(
+            ClientApiUrl::parse("http://127.0.0.1:8080"),
+            ClientApiUrl {
+                base_url: "http://127.0.0.1:8080".to_string(),
+                unix_socket: None,
+                display: "http://127.0.0.1:8080".to_string(),
+            }
+        );
+        assert_eq!(
+            ClientApiUrl::parse("unix:///run/cruncher/api.sock"),
+            ClientApiUrl {
+                base_url: "http://localhost".to_string(),
+                unix_socket: Some(PathBuf::from("/run/cruncher/api.sock")),
+                display: "unix:///run/cruncher/api.sock".to_string(),
+            }
+        );
+    }
+
+    const SECRET: &str = "cruncher-secret";
+
+    /// Mock client API accepting only requests signed with [`SECRET`].
+    struct ValidSignature;
+
+    impl wiremock::Match for ValidSignature {
+        fn matches(&self, request: &Request) -> bool {
+            let header = |name| {
+                request
+                    .headers
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+            };
+            match (header(TIMESTAMP_HEADER), header(SIGNATURE_HEADER)) {
+                (Some(timestamp), Some(received)) => {
+                    let expected = signature(
+                        SECRET,
+                        timestamp,
+                        request.method.as_str(),
+                        request.url.path(),
+                        &request.body,
+                    );
+                    expected == received
+                }
+                _ => false,
+            }
+        }
+    }
+
+    async fn authenticating_server() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/runners/start"))
+            .and(ValidSignature)
+            .respond_with(ResponseTemplate::new(200))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/runners/target/set"))
+            .and(ValidSignature)
+            .respond_with(ResponseTemplate::new(200))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/runners/stop"))
+            .and(header("Authorization", "Bearer token"))
+            .respond_with(ResponseTemplate::new(200))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(401))
+            .with_priority(10)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[actix_rt::test]
+    async fn test_requests_without_valid_credentials_rejected() {
+        let server = authenticating_server().await;
+        let client = |auth| authenticated_client(&server.uri(), 0, auth);
+        let hmac = |secret: &str| {
+            Some(ClientApiAuth::HmacSha256 {
+                secret: secret.to_string(),
+            })
+        };
+        let bearer = |token: &str| {
+            Some(ClientApiAuth::Bearer {
+                token: token.to_string(),
+            })
+        };
+        let rejected = |result: Result<RunnersResponse, CruncherError>| matches!(result, Err(CruncherError::BackendRejected(message)) if message.contains("401"));
+        let target = WorkTarget::Factory("0x01".to_string());
+
+        assert!(client(hmac(SECRET)).start_work().await.is_ok());
+        assert_eq!(
+            client(hmac(SECRET))
+                .send_work_target(target.clone(), None)
+                .await,
+            Ok(())
+        );
+        assert!(rejected(client(None).start_work().await));
+        assert!(rejected(client(hmac("other")).start_work().await));
+        assert!(client(hmac("other"))
+            .send_work_target(target, None)
+            .await
+            .is_err());
+
+        assert!(client(bearer("token")).stop_work().await.is_ok());
+        assert!(rejected(client(None).stop_work().await));
+        assert!(rejected(client(bearer("other")).stop_work().await));
+    }
+
+    #[test]
+    fn test_invalid_token_fails_client() {
+        let auth = Some(ClientApiAuth::Bearer {
+            token: "new\nline".to_string(),
+        });
+        let config = HttpClientConfig {
+            connect_timeout: Duration::from_secs(1),
+            timeout: Duration::from_secs(1),
+            retry: RetryConfig {
+                max_retries: 0,
+                initial_backoff: Duration::from_millis(1),
+            },
+            auth,
+        };
+        assert!(HttpClient::new(ClientApiUrl::parse("http://127.0.0.1:1"), config).is_err());
+    }
+
+    #[cfg(unix)]
+    #[actix_rt::test]
+    async fn test_request_over_unix_socket() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::net::UnixListener;
+
+        let socket = std::env::temp_dir().join(format!("cruncher-api-{}.sock", std::process::id()));
+        std::fs::remove_file(&socket).ok();
+        let listener = UnixListener::bind(&socket).unwrap();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let body = r#"{"runners":[{"id":0,"status":"started"}]}"#;
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+            request_line
+        });
+
+        let url = format!("unix://{}", socket.display());
+        let response = http_client(&url).start_work().await.unwrap();
+        assert_eq!(
+            response.to_stdout(),
+            r#"{"runners":[{"id":0,"status":"started"}]}"#
+        );
+        assert!(server
+            .join()
+            .unwrap()
+            .starts_with("POST /api/runners/start HTTP/1.1"));
+
+        // Socket is gone, so error points to it rather than to request host
+        std::fs::remove_file(&socket).unwrap();
+        let Err(CruncherError::BackendUnreachable(message)) = http_client(&url).start_work().await
+        else {
+            panic!("Expected unreachable client API");
         };
-        Err(RpcMessageError::Activity(format!(
-            "Failed to stop runners: {} {}",
-            status, text
-        )))
+        assert!(message.contains(&url), "{message}");
+        assert!(!message.contains(UNIX_SOCKET_BASE_URL), "{message}");
     }
 }
@@ -0,0 +1,373 @@
+//! Time-sliced rotation across multiple work targets
+//!
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use ya_client_model::activity::activity_state::State;
+
+use crate::error::CruncherError;
+use crate::requests::WorkTarget;
+use crate::work_target::{apply_work_target, parse_work_target, sanitize_work_target};
+use crate::ExeUnitContext;
+
+/// Rotation period split between targets given with `--weight`
+const DEFAULT_WEIGHTS_PERIOD: Duration = Duration::from_secs(600);
+/// Shortest time a work target is crunched for in a rotation round
+const MIN_SLICE: Duration = Duration::from_secs(1);
+
+/// Work target crunched for `slice` in every rotation round.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RotationEntry {
+    pub target: WorkTarget,
+    pub slice: Duration,
+}
+
+/// Usage accumulated while a work target was active.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetUsage {
+    pub target: WorkTarget,
+    pub slice_sec: f64,
+    pub active_sec: f64,
+    pub tera_hash: f64,
+}
+
+/// Keys of a JSON work target, in order of `set_work_target` arguments
+const TARGET_KEYS: [&str; 4] = ["kind", "value", "init_code_hash", "salt_prefix"];
+
+/// Parses `set_work_targets` arguments, e.g.
+/// `--slice 60 factory 0x... --slice 120 public_key_base 0x...` or
+/// `--period 600 --weight 1 factory 0x... --weight 3 factory 0x...`,
+/// or the same as a single JSON object, e.g.
+/// `{"period": 600, "targets": [{"weight": 1, "kind": "factory", "value": "0x..."}]}`.
+/// Work targets are sanitized.
+pub fn parse_rotation(args: &[String]) -> Result<Vec<RotationEntry>, CruncherError> {
+    let json_args;
+    let args = match args {
+        [arg] if arg.trim_start().starts_with('{') => {
+            json_args = args_from_json(arg)?;
+            json_args.as_slice()
+        }
+        args => args,
+    };
+    let mut period = DEFAULT_WEIGHTS_PERIOD;
+    let mut slices = Vec::new();
+    let mut weights = Vec::new();
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .and_then(|value| value.parse::<f64>().ok())
+            .filter(|value| value.is_finite() && *value > 0.0)
+            .ok_or_else(|| {
+                CruncherError::InvalidArgument(format!("{arg} expects positive number"))
+            })?;
+        if arg == "--period" {
+            period = seconds(arg, value)?;
+            continue;
+        }
+
+        let mut target_args = Vec::new();
+        while let Some(target_arg) = args.next_if(|arg| !arg.starts_with("--")) {
+            target_args.push(target_arg.clone());
+        }
+        let target = sanitize_work_target(parse_work_target(&target_args)?)?;
+        match arg.as_str() {
+            "--slice" => slices.push((target, value)),
+            "--weight" => weights.push((target, value)),
+            arg => {
+                return Err(CruncherError::InvalidArgument(format!(
+                    "Unknown work targets argument: {arg}"
+                )))
+            }
+        }
+    }
+
+    if !slices.is_empty() && !weights.is_empty() {
+        return Err(CruncherError::InvalidArgument(
+            "Work targets have to be given either with slices or with weights".to_string(),
+        ));
+    }
+    let total_weight: f64 = weights.iter().map(|(_, weight)| weight).sum();
+    let mut entries = Vec::new();
+    for (target, slice) in slices {
+        entries.push(RotationEntry {
+            target,
+            slice: seconds("--slice", slice)?,
+        });
+    }
+    for (target, weight) in weights {
+        entries.push(RotationEntry {
+            target,
+            slice: seconds("--weight", period.as_secs_f64() * (weight / total_weight))?,
+        });
+    }
+    if let Some(entry) = entries.iter().find(|entry| entry.slice < MIN_SLICE) {
+        return Err(CruncherError::InvalidArgument(format!(
+            "Work target {} gets {:?}, shorter than minimal slice {:?}",
+            entry.target, entry.slice, MIN_SLICE
+        )));
+    }
+    if entries.is_empty() {
+        return Err(CruncherError::InvalidArgument(
+            "Missing work targets".to_string(),
+        ));
+    }
+    Ok(entries)
+}
+
+fn seconds(arg: &str, value: f64) -> Result<Duration, CruncherError> {
+    Duration::try_from_secs_f64(value)
+        .map_err(|e| CruncherError::InvalidArgument(format!("{arg} out of range: {e}")))
+}
+
+fn args_from_json(arg: &str) -> Result<Vec<String>, CruncherError> {
+    let invalid = |message: String| CruncherError::InvalidArgument(message);
+    let to_arg = |key: &str, value: &Value| match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Number(value) => Ok(value.to_string()),
+        _ => Err(invalid(format!(
+            "Work targets argument {key} has to be a string or a number"
+        ))),
+    };
+
+    let object = serde_json::from_str::<Map<String, Value>>(arg)
+        .map_err(|e| invalid(format!("Invalid JSON work targets: {e}")))?;
+    let mut args = Vec::new();
+    for (key, value) in &object {
+        match (key.as_str(), value) {
+            ("period", value) => args.extend(["--period".to_string(), to_arg(key, value)?]),
+            ("targets", Value::Array(targets)) => {
+                for target in targets {
+                    let target = target.as_object().ok_or_else(|| {
+                        invalid("Work target has to be a JSON object".to_string())
+                    })?;
+                    if let Some(unknown) = target.keys().find(|key| {
+                        !["slice", "weight"].contains(&key.as_str())
+                            && !TARGET_KEYS.contains(&key.as_str())
+                    }) {
+                        return Err(invalid(format!("Unknown work target argument {unknown}")));
+                    }
+                    for share in ["slice", "weight"] {
+                        if let Some(value) = target.get(share) {
+                            args.extend([format!("--{share}"), to_arg(share, value)?]);
+                        }
+                    }
+                    for key in TARGET_KEYS {
+                        if let Some(value) = target.get(key) {
+                            args.push(to_arg(key, value)?);
+                        }
+                    }
+                }
+            }
+            ("targets", _) => return Err(invalid("targets has to be a JSON array".to_string())),
+            (key, _) => return Err(invalid(format!("Unknown work targets argument {key}"))),
+        }
+    }
+    Ok(args)
+}
+
+/// Rotation currently driven by the timer, together with per-target usage.
+pub struct Rotation {
+    entries: Vec<RotationEntry>,
+    usage: Vec<TargetUsage>,
+    /// Index of the target successfully sent to client API
+    active: Option<usize>,
+    active_since: Instant,
+    tera_hash_since: f64,
+    /// Replaced by a single work target, usage is kept for reporting
+    stopped: bool,
+    /// Timer task applying the targets
+    task: Option<JoinHandle<()>>,
+}
+
+impl Rotation {
+    fn new(entries: Vec<RotationEntry>) -> Self {
+        let usage = entries
+            .iter()
+            .map(|entry| TargetUsage {
+                target: entry.target.clone(),
+                slice_sec: entry.slice.as_secs_f64(),
+                active_sec: 0.0,
+                tera_hash: 0.0,
+            })
+            .collect();
+        Self {
+            entries,
+            usage,
+            active: None,
+            active_since: Instant::now(),
+            tera_hash_since: 0.0,
+            stopped: false,
+            task: None,
+        }
+    }
+
+    /// Attributes usage since the last call to the active target.
+    fn account(&mut self, tera_hash: f64) {
+        if let Some(usage) = self.active.and_then(|active| self.usage.get_mut(active)) {
+            usage.active_sec += self.active_since.elapsed().as_secs_f64();
+            usage.tera_hash += (tera_hash - self.tera_hash_since).max(0.0);
+        }
+        self.active_since = Instant::now();
+        self.tera_hash_since = tera_hash;
+    }
+
+    fn activate(&mut self, index: Option<usize>, tera_hash: f64) {
+        self.account(tera_hash);
+        self.active = index;
+    }
+
+    /// Per-target usage up to now.
+    pub fn usage(&mut self, tera_hash: f64) -> Vec<TargetUsage> {
+        self.account(tera_hash);
+        self.usage.clone()
+    }
+}
+
+/// Replaces running rotation with a new one and starts its timer.
+pub async fn start_rotation(ctx: &ExeUnitContext, entries: Vec<RotationEntry>) {
+    stop_rotation(ctx).await;
+    log::info!("Rotating between {} work targets", entries.len());
+    let rotation = Rc::new(RefCell::new(Rotation::new(entries)));
+    *ctx.rotation.borrow_mut() = Some(rotation.clone());
+
+    let task_rotation = rotation.clone();
+    let ctx = ctx.clone();
+    let task = tokio::task::spawn_local(async move {
+        let rotation = task_rotation;
+        let is_current = |ctx: &ExeUnitContext| {
+            ctx.rotation
+                .borrow()
+                .as_ref()
+                .is_some_and(|current| Rc::ptr_eq(current, &rotation))
+                && !rotation.borrow().stopped
+                && ctx.state.borrow().state.0 != State::Terminated
+        };
+        let entries = rotation.borrow().entries.clone();
+        for (index, entry) in entries.iter().enumerate().cycle() {
+            if !is_current(&ctx) {
+                break;
+            }
+            let active = match apply_work_target(&ctx, entry.target.clone()).await {
+                Ok(()) => {
+                    log::info!(
+                        "Rotated work target to {} for {:?}",
+                        entry.target,
+                        entry.slice
+                    );
+                    Some(index)
+                }
+                Err(e) => {
+                    log::error!("Failed to rotate work target to {}: {e}", entry.target);
+                    None
+                }
+            };
+            let tera_hash = ctx.current_usage.lock().await[ctx.tera_hash_pos];
+            if !is_current(&ctx) {
+                break;
+            }
+            rotation.borrow_mut().activate(active, tera_hash);
+            tokio::time::sleep(entry.slice).await;
+        }
+        let tera_hash = ctx.current_usage.lock().await[ctx.tera_hash_pos];
+        rotation.borrow_mut().activate(None, tera_hash);
+        log::debug!("Work target rotation finished");
+    });
+    rotation.borrow_mut().task = Some(task);
+}
+
+/// Stops running rotation, if any. Its usage stays available until a new rotation starts.
+/// Waits for the work target being applied by the rotation, so no stale target is sent
+/// or saved in the session afterwards.
+pub async fn stop_rotation(ctx: &ExeUnitContext) {
+    let _lock = ctx.work_target_lock.lock().await;
+    let tera_hash = ctx.current_usage.lock().await[ctx.tera_hash_pos];
+    if let Some(rotation) = ctx.rotation.borrow().as_ref() {
+        let mut rotation = rotation.borrow_mut();
+        if let Some(task) = rotation.task.take() {
+            task.abort();
+        }
+        if !rotation.stopped {
+            rotation.stopped = true;
+            rotation.activate(None, tera_hash);
+            log::info!("Work target rotation stopped");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FACTORY_A: &str = "0x9e3f1b2a4c5d6e7f8091a2b3c4d5e6f708192a3b";
+    const FACTORY_B: &str = "0x0000000000000000000000000000000000000001";
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_rotation() {
+        let slices = parse_rotation(&args(&format!(
+            "--slice 60 factory {FACTORY_A} --slice 30 default"
+        )))
+        .unwrap();
+        assert_eq!(
+            slices,
+            vec![
+                RotationEntry {
+                    target: WorkTarget::Factory(FACTORY_A.to_string()),
+                    slice: Duration::from_secs(60),
+                },
+                RotationEntry {
+                    target: WorkTarget::Default,
+                    slice: Duration::from_secs(30),
+                },
+            ]
+        );
+
+        let weights = parse_rotation(&args(&format!(
+            "--period 100 --weight 1 factory {FACTORY_A} --weight 3 factory {FACTORY_B}"
+        )))
+        .unwrap();
+        assert_eq!(weights[0].slice, Duration::from_secs(25));
+        assert_eq!(weights[1].slice, Duration::from_secs(75));
+
+        assert!(parse_rotation(&args(&format!(
+            "--slice 60 factory {FACTORY_A} --weight 1 default"
+        )))
+        .is_err());
+        assert!(parse_rotation(&[]).is_err());
+
+        let json = parse_rotation(&[format!(
+            r#"{{"period": 100, "targets": [{{"weight": 1, "kind": "factory", "value": "{FACTORY_A}"}}, {{"weight": 3, "kind": "default"}}]}}"#
+        )])
+        .unwrap();
+        assert_eq!(json[1].target, WorkTarget::Default);
+        assert_eq!(json[1].slice, Duration::from_secs(75));
+    }
+
+    #[test]
+    fn test_reject_rotation_out_of_range() {
+        for rotation in [
+            format!("--slice 1e300 factory {FACTORY_A}"),
+            format!("--period 1e300 --weight 1 factory {FACTORY_A}"),
+            format!("--slice 0.5 factory {FACTORY_A}"),
+            format!("--period 10 --weight 1 factory {FACTORY_A} --weight 1e300 default"),
+            format!("--period 10 --weight 1e308 factory {FACTORY_A} --weight 1e308 default"),
+        ] {
+            assert!(
+                matches!(
+                    parse_rotation(&args(&rotation)),
+                    Err(CruncherError::InvalidArgument(_))
+                ),
+                "{rotation} accepted"
+            );
+        }
+    }
+}
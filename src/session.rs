@@ -0,0 +1,335 @@
+//! Work session persisted in `work_dir` and re-applied after client API restarts
+//!
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use ya_client_model::activity::activity_state::State;
+
+use crate::error::CruncherError;
+use crate::pattern::Pattern;
+use crate::requests::{
+    get_runners, get_work_target, send_work_target, start_work, RunnersResponse, WorkTarget,
+    RUNNER_STARTED,
+};
+use crate::work_target::sanitize_work_target;
+use crate::ExeUnitContext;
+
+const SESSION_FILE: &str = "session.json";
+
+/// Work target and runners state requested by the requestor.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    /// Last sanitized work target sent to client API
+    pub work_target: Option<WorkTarget>,
+    /// Runners were started and not stopped since
+    pub started: bool,
+    /// Last validated pattern, sent together with the work target
+    pub pattern: Option<Pattern>,
+}
+
+impl Session {
+    fn path(work_dir: &Path) -> PathBuf {
+        work_dir.join(SESSION_FILE)
+    }
+
+    /// Loads session of previous runtime instance. Missing or broken file gives empty session.
+    pub fn load(work_dir: &Path) -> Self {
+        let path = Self::path(work_dir);
+        let Ok(content) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        match serde_json::from_str(&content) {
+            Ok(session) => {
+                log::info!("Loaded work session from {}", path.display());
+                session
+            }
+            Err(e) => {
+                log::warn!("Ignoring invalid session file {}: {e}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, work_dir: &Path) -> Result<(), CruncherError> {
+        let path = Self::path(work_dir);
+        let content = serde_json::to_string(self)
+            .map_err(|e| CruncherError::Internal(format!("Failed to serialize session: {e}")))?;
+        fs::write(&path, content).map_err(|e| {
+            CruncherError::Internal(format!("Failed to save session to {}: {e}", path.display()))
+        })
+    }
+}
+
+/// Updates session and persists it in `work_dir`.
+pub fn update_session(
+    ctx: &ExeUnitContext,
+    update: impl FnOnce(&mut Session),
+) -> Result<(), CruncherError> {
+    let mut session = ctx.session.borrow_mut();
+    update(&mut session);
+    session.save(&ctx.work_dir)
+}
+
+/// Work target reported by client API.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum BackendTarget {
+    /// Response not understood, nothing can be said about the session
+    Unknown,
+    /// No work target set, e.g. after restart
+    Missing,
+    Target(WorkTarget),
+}
+
+impl BackendTarget {
+    pub(crate) fn parse(value: serde_json::Value) -> Self {
+        if value.is_null() {
+            return BackendTarget::Missing;
+        }
+        match serde_json::from_value::<WorkTarget>(value.clone())
+            .map_err(|e| CruncherError::BackendRejected(e.to_string()))
+            .and_then(sanitize_work_target)
+        {
+            Ok(target) => BackendTarget::Target(target),
+            Err(e) => {
+                log::debug!("Unknown client API work target {value}: {e}");
+                BackendTarget::Unknown
+            }
+        }
+    }
+
+    /// Whether client API target differs from the one set by the runtime. Without target
+    /// set by the runtime any target is expected. `None` when client API target is unknown.
+    pub(crate) fn mismatch(&self, runtime: Option<&WorkTarget>) -> Option<bool> {
+        match (runtime, self) {
+            (None, _) => Some(false),
+            (_, BackendTarget::Unknown) => None,
+            (Some(_), BackendTarget::Missing) => Some(true),
+            (Some(runtime), BackendTarget::Target(target)) => Some(runtime != target),
+        }
+    }
+}
+
+/// Whether any runner is started. `None` when runners state is unknown.
+fn runners_started(runners: &RunnersResponse) -> Option<bool> {
+    match runners {
+        RunnersResponse::Runners(status) => Some(
+            status
+                .runners
+                .iter()
+                .any(|runner| runner.status == RUNNER_STARTED),
+        ),
+        RunnersResponse::Raw(_) => None,
+    }
+}
+
+/// Whether client API lost the work target or started runners of the session.
+/// Unknown state is never considered lost.
+fn session_lost(session: &Session, target: &BackendTarget, started: Option<bool>) -> bool {
+    let target_lost = target.mismatch(session.work_target.as_ref()) == Some(true);
+    let started_lost = session.started && started == Some(false);
+    target_lost || started_lost
+}
+
+/// Re-applies session persisted by previous runtime instance.
+pub fn spawn_session_restore(ctx: &ExeUnitContext) {
+    if *ctx.session.borrow() == Session::default() {
+        return;
+    }
+    let ctx = ctx.clone();
+    tokio::task::spawn_local(async move {
+        log::info!("Restoring persisted work session");
+        if let Err(e) = reapply_session(&ctx).await {
+            log::error!("Failed to restore work session: {e}");
+        }
+    });
+}
+
+/// Periodically compares client API state with the session. Client API which
+/// came back after being unreachable, or lost work target or started runners,
+/// is considered restarted and gets session re-applied.
+pub fn spawn_session_monitor(ctx: ExeUnitContext, interval: Duration) {
+    log::info!("Checking client API session every {}s", interval.as_secs());
+    tokio::task::spawn_local(async move {
+        let mut unreachable = false;
+        loop {
+            tokio::time::sleep(interval).await;
+            if ctx.state.borrow().state.0 == State::Terminated {
+                break;
+            }
+            let session = ctx.session.borrow().clone();
+            if session == Session::default() {
+                continue;
+            }
+
+            let target = match get_work_target().await {
+                Ok(target) => BackendTarget::parse(target),
+                Err(CruncherError::BackendUnreachable(_)) => {
+                    unreachable = true;
+                    continue;
+                }
+                Err(e) => {
+                    log::warn!("Failed to check client API session: {e}");
+                    continue;
+                }
+            };
+            let started = match get_runners().await {
+                Ok(runners) => runners_started(&runners),
+                Err(e) => {
+                    log::debug!("Failed to check client API runners: {e}");
+                    None
+                }
+            };
+
+            if unreachable || session_lost(&session, &target, started) {
+                log::warn!("Client API restart detected. Re-applying work session");
+                match reapply_session(&ctx).await {
+                    Ok(()) => unreachable = false,
+                    Err(e) => log::error!("Failed to re-apply work session: {e}"),
+                }
+            }
+        }
+    });
+}
+
+/// Re-applies session still allowed by the policy, which could change since it was saved.
+async fn reapply_session(ctx: &ExeUnitContext) -> Result<(), CruncherError> {
+    let _lock = ctx.work_target_lock.lock().await;
+    let session = ctx.session.borrow().clone();
+    let policy = &ctx.runtime_config.policy;
+    if session.started {
+        policy.check_runners_target(session.work_target.as_ref())?;
+    } else if let Some(work_target) = &session.work_target {
+        policy.check_work_target(work_target)?;
+    }
+    if let Some(work_target) = &session.work_target {
+        send_work_target(work_target.clone(), session.pattern.as_ref()).await?;
+        log::info!("Re-applied work target {}", work_target);
+    }
+    if session.started {
+        start_work().await?;
+        log::info!("Re-started runners");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_roundtrip() {
+        let work_dir =
+            std::env::temp_dir().join(format!("cruncher-session-{}", std::process::id()));
+        fs::create_dir_all(&work_dir).unwrap();
+        assert_eq!(Session::load(&work_dir), Session::default());
+
+        let session = Session {
+            work_target: Some(WorkTarget::Factory(
+                "0x9e3f1b2a4c5d6e7f8091a2b3c4d5e6f708192a3b".to_string(),
+            )),
+            started: true,
+            pattern: Some(Pattern {
+                prefix: Some("dead".to_string()),
+                ..Pattern::default()
+            }),
+        };
+        session.save(&work_dir).unwrap();
+        assert_eq!(Session::load(&work_dir), session);
+        fs::remove_dir_all(&work_dir).unwrap();
+    }
+
+    const FACTORY: &str = "0x9e3f1b2a4c5d6e7f8091a2b3c4d5e6f708192a3b";
+
+    #[test]
+    fn test_parse_backend_target() {
+        assert_eq!(
+            BackendTarget::parse(serde_json::Value::Null),
+            BackendTarget::Missing
+        );
+        assert_eq!(
+            BackendTarget::parse(serde_json::json!("default")),
+            BackendTarget::Target(WorkTarget::Default)
+        );
+        assert_eq!(
+            BackendTarget::parse(
+                serde_json::json!({ "factory": FACTORY.to_uppercase().replacen("0X", "0x", 1) })
+            ),
+            BackendTarget::Target(WorkTarget::Factory(FACTORY.to_string()))
+        );
+        assert_eq!(
+            BackendTarget::parse(serde_json::json!({"unknown": 1})),
+            BackendTarget::Unknown
+        );
+        assert_eq!(
+            BackendTarget::parse(serde_json::json!({"factory": "0xzz"})),
+            BackendTarget::Unknown
+        );
+    }
+
+    #[test]
+    fn test_backend_target_mismatch() {
+        let factory = WorkTarget::Factory(FACTORY.to_string());
+        let target = BackendTarget::Target(factory.clone());
+        assert_eq!(target.mismatch(Some(&factory)), Some(false));
+        assert_eq!(target.mismatch(Some(&WorkTarget::Default)), Some(true));
+        assert_eq!(target.mismatch(None), Some(false));
+        assert_eq!(BackendTarget::Missing.mismatch(Some(&factory)), Some(true));
+        assert_eq!(BackendTarget::Unknown.mismatch(Some(&factory)), None);
+        assert_eq!(BackendTarget::Unknown.mismatch(None), Some(false));
+    }
+
+    #[test]
+    fn test_session_lost() {
+        let factory = WorkTarget::Factory(FACTORY.to_string());
+        let session = Session {
+            work_target: Some(factory.clone()),
+            started: true,
+            pattern: None,
+        };
+        let target = BackendTarget::Target(factory.clone());
+        assert!(!session_lost(&session, &target, Some(true)));
+        assert!(!session_lost(&session, &target, None));
+        assert!(session_lost(&session, &target, Some(false)));
+        assert!(session_lost(&session, &BackendTarget::Missing, Some(true)));
+        assert!(session_lost(
+            &session,
+            &BackendTarget::Target(WorkTarget::Default),
+            Some(true)
+        ));
+        assert!(!session_lost(&session, &BackendTarget::Unknown, None));
+
+        let default = Session {
+            work_target: Some(WorkTarget::Default),
+            started: false,
+            pattern: None,
+        };
+        assert!(session_lost(&default, &BackendTarget::Missing, None));
+        assert!(session_lost(
+            &default,
+            &BackendTarget::Target(factory),
+            None
+        ));
+        assert!(!session_lost(
+            &default,
+            &BackendTarget::Target(WorkTarget::Default),
+            Some(false)
+        ));
+    }
+
+    #[test]
+    fn test_runners_started() {
+        let runners = |body: &str| runners_started(&RunnersResponse::parse(body.to_string()));
+        assert_eq!(
+            runners(r#"{"runners":[{"id":0,"status":"stopped"},{"id":1,"status":"started"}]}"#),
+            Some(true)
+        );
+        assert_eq!(
+            runners(r#"{"runners":[{"id":0,"status":"stopped"}]}"#),
+            Some(false)
+        );
+        assert_eq!(runners("Runners started"), None);
+    }
+}
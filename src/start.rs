@@ -0,0 +1,142 @@
+//! Handling of `ExeScriptCommand::Start` arguments
+//!
+
+use std::time::Duration;
+use ya_client_model::activity::activity_state::State;
+use ya_service_bus::typed as gsb;
+
+use crate::error::CruncherError;
+use crate::requests::WorkTarget;
+use crate::work_target::{parse_work_target, sanitize_work_target};
+use crate::{set_usage_msg, ExeUnitContext};
+
+/// Initial session configuration, e.g.
+/// `--work-target factory 0x... --auto-start --usage-interval 60`
+#[derive(Debug, Default)]
+pub struct StartArgs {
+    /// Work target set before runners are started
+    pub work_target: Option<WorkTarget>,
+    /// Start runners right after Start command
+    pub auto_start: bool,
+    /// Interval of periodic usage reports
+    pub usage_interval: Option<Duration>,
+}
+
+impl StartArgs {
+    pub fn parse(args: &[String]) -> Result<Self, CruncherError> {
+        let mut start_args = StartArgs::default();
+        let mut args = args.iter().peekable();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--work-target" => {
+                    let mut target_args = Vec::new();
+                    while let Some(target_arg) = args.next_if(|arg| !arg.starts_with("--")) {
+                        target_args.push(target_arg.clone());
+                    }
+                    let work_target = sanitize_work_target(parse_work_target(&target_args)?)?;
+                    start_args.work_target = Some(work_target);
+                }
+                "--auto-start" => start_args.auto_start = true,
+                "--usage-interval" => {
+                    let interval = args
+                        .next()
+                        .and_then(|interval| interval.parse::<u64>().ok())
+                        .filter(|interval| *interval > 0)
+                        .ok_or_else(|| {
+                            CruncherError::InvalidArgument(
+                                "Usage interval expects positive number of seconds".to_string(),
+                            )
+                        })?;
+                    start_args.usage_interval = Some(Duration::from_secs(interval));
+                }
+                arg => {
+                    return Err(CruncherError::InvalidArgument(format!(
+                        "Unknown Start argument: {arg}"
+                    )))
+                }
+            }
+        }
+        Ok(start_args)
+    }
+}
+
+/// Periodically reports current usage until activity is terminated.
+/// Replaces reporter spawned by previous Start.
+pub fn spawn_usage_reporter(ctx: &ExeUnitContext, interval: Duration) {
+    log::info!("Reporting usage every {}s", interval.as_secs());
+    let reporter_ctx = ctx.clone();
+    let reporter = tokio::task::spawn_local(async move {
+        let ctx = reporter_ctx;
+        loop {
+            tokio::time::sleep(interval).await;
+            if ctx.state.borrow().state.0 == State::Terminated {
+                break;
+            }
+            set_usage_msg(
+                &gsb::service(ctx.report_url.clone()),
+                &ctx.activity_id,
+                ctx.current_usage.lock().await.clone(),
+            )
+            .await;
+        }
+    });
+    if let Some(previous) = ctx.usage_reporter.replace(Some(reporter)) {
+        previous.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FACTORY: &str = "0x9e3f1b2a4c5d6e7f8091a2b3c4d5e6f708192a3b";
+
+    fn parse(args: &str) -> Result<StartArgs, CruncherError> {
+        StartArgs::parse(
+            &args
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn test_parse_start_args() {
+        let start_args = parse(&format!(
+            "--work-target factory {FACTORY} --auto-start --usage-interval 60"
+        ))
+        .unwrap();
+        assert_eq!(
+            start_args.work_target,
+            Some(WorkTarget::Factory(FACTORY.to_string()))
+        );
+        assert!(start_args.auto_start);
+        assert_eq!(start_args.usage_interval, Some(Duration::from_secs(60)));
+
+        let start_args = parse("").unwrap();
+        assert_eq!(start_args.work_target, None);
+        assert!(!start_args.auto_start);
+        assert_eq!(start_args.usage_interval, None);
+    }
+
+    #[test]
+    fn test_parse_start_args_rejects_invalid() {
+        for args in [
+            "--unknown",
+            "auto-start",
+            "--usage-interval",
+            "--usage-interval 0",
+            "--usage-interval -5",
+            "--usage-interval 1.5",
+            "--usage-interval abc",
+            "--work-target",
+            "--work-target factory 0xzz",
+            "--work-target unknown 0x01",
+        ] {
+            assert!(
+                matches!(parse(args), Err(CruncherError::InvalidArgument(_))),
+                "{args} accepted"
+            );
+        }
+    }
+}
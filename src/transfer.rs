@@ -0,0 +1,165 @@
+//! Handling of `ExeScriptCommand::Transfer`
+//!
+
+use actix::Addr;
+use std::path::{Component, Path};
+use ya_client_model::activity::TransferArgs;
+use ya_transfer::transfer::{TransferResource, TransferService};
+
+use crate::error::CruncherError;
+
+const CONTAINER_SCHEME: &str = "container:";
+const FILE_SCHEME: &str = "file";
+
+pub async fn transfer(
+    transfers: &Addr<TransferService>,
+    work_dir: &Path,
+    from: &str,
+    to: &str,
+    args: &TransferArgs,
+) -> Result<(), CruncherError> {
+    let from = resolve_url(work_dir, from)?;
+    let to = resolve_url(work_dir, to)?;
+    log::info!("Transferring {from} to {to}");
+
+    transfers
+        .send(TransferResource {
+            from,
+            to,
+            args: args.clone(),
+            progress_config: None,
+        })
+        .await
+        .map_err(|e| CruncherError::Internal(format!("Transfer service error: {e}")))?
+        .map_err(|e| CruncherError::Internal(format!("Transfer failed: {e}")))
+}
+
+/// Runtime has no container, so `container:` urls are resolved against `work_dir`.
+/// `file:` urls are accepted only inside `work_dir`. Other urls are passed to
+/// `TransferService` unchanged.
+fn resolve_url(work_dir: &Path, url: &str) -> Result<String, CruncherError> {
+    let outside =
+        || CruncherError::InvalidArgument(format!("Transfer path outside of work dir: {url}"));
+    if let Some(path) = url.strip_prefix(CONTAINER_SCHEME) {
+        return work_dir_url(work_dir, Path::new(path.trim_start_matches('/')), url);
+    }
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|e| CruncherError::InvalidArgument(format!("Invalid transfer url {url}: {e}")))?;
+    if parsed.scheme() != FILE_SCHEME {
+        return Ok(url.to_string());
+    }
+    let path = parsed.to_file_path().map_err(|_| outside())?;
+    let path = path.strip_prefix(work_dir).map_err(|_| outside())?;
+    work_dir_url(work_dir, path, url)
+}
+
+/// File url of `path` relative to `work_dir`, which cannot leave `work_dir`.
+fn work_dir_url(work_dir: &Path, path: &Path, url: &str) -> Result<String, CruncherError> {
+    if path
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return Err(CruncherError::InvalidArgument(format!(
+            "Transfer path outside of work dir: {url}"
+        )));
+    }
+    reqwest::Url::from_file_path(work_dir.join(path))
+        .map(|url| url.to_string())
+        .map_err(|_| CruncherError::InvalidArgument(format!("Invalid transfer path: {url}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix::Actor;
+    use std::path::PathBuf;
+    use ya_transfer::transfer::TransferServiceContext;
+
+    #[test]
+    fn test_resolve_container_url() {
+        let work_dir = std::env::temp_dir().join("work");
+        let resolved = resolve_url(&work_dir, "container:/input/targets.txt").unwrap();
+        let expected = reqwest::Url::from_file_path(work_dir.join("input").join("targets.txt"))
+            .unwrap()
+            .to_string();
+        assert_eq!(resolved, expected);
+
+        assert!(resolve_url(&work_dir, "container:/../secret").is_err());
+        assert!(resolve_url(&PathBuf::from("work"), "container:/a/../../b").is_err());
+    }
+
+    #[test]
+    fn test_resolve_file_url() {
+        let work_dir = std::env::temp_dir().join("work");
+        let inside = reqwest::Url::from_file_path(work_dir.join("output.txt"))
+            .unwrap()
+            .to_string();
+        assert_eq!(resolve_url(&work_dir, &inside).unwrap(), inside);
+
+        assert!(resolve_url(&work_dir, "file:///etc/shadow").is_err());
+        assert!(resolve_url(&work_dir, "file:///tmp/targets.txt").is_err());
+        assert!(resolve_url(&work_dir, &format!("{inside}/../../../etc/shadow")).is_err());
+        assert!(resolve_url(&work_dir, &format!("{inside}/%2e%2e/%2e%2e/etc/shadow")).is_err());
+        assert!(resolve_url(&work_dir, "/tmp").is_err());
+
+        assert_eq!(
+            resolve_url(&work_dir, "https://example.com/targets.txt").unwrap(),
+            "https://example.com/targets.txt"
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_push_and_pull_file_in_work_dir() {
+        let work_dir =
+            std::env::temp_dir().join(format!("cruncher-transfer-{}", std::process::id()));
+        std::fs::create_dir_all(&work_dir).unwrap();
+        std::fs::write(work_dir.join("targets.txt"), "0xabc").unwrap();
+        let transfers = TransferService::new(TransferServiceContext {
+            work_dir: work_dir.clone(),
+            cache_dir: work_dir.join("cache"),
+            ..TransferServiceContext::default()
+        })
+        .start();
+        let file_url = |name: &str| {
+            reqwest::Url::from_file_path(work_dir.join(name))
+                .unwrap()
+                .to_string()
+        };
+        let args = TransferArgs::default();
+
+        let pushed = transfer(
+            &transfers,
+            &work_dir,
+            &file_url("targets.txt"),
+            "container:/input/targets.txt",
+            &args,
+        )
+        .await;
+        let pulled = transfer(
+            &transfers,
+            &work_dir,
+            "container:/input/targets.txt",
+            &file_url("result.txt"),
+            &args,
+        )
+        .await;
+        let outside = transfer(
+            &transfers,
+            &work_dir,
+            "container:/input/targets.txt",
+            "file:///tmp/cruncher-transfer-escape.txt",
+            &args,
+        )
+        .await;
+        let input = std::fs::read_to_string(work_dir.join("input").join("targets.txt"));
+        let result = std::fs::read_to_string(work_dir.join("result.txt"));
+        std::fs::remove_dir_all(&work_dir).ok();
+
+        assert_eq!(pushed, Ok(()));
+        assert_eq!(pulled, Ok(()));
+        assert_eq!(input.unwrap(), "0xabc");
+        assert_eq!(result.unwrap(), "0xabc");
+        assert!(matches!(outside, Err(CruncherError::InvalidArgument(_))));
+        assert!(!Path::new("/tmp/cruncher-transfer-escape.txt").exists());
+    }
+}
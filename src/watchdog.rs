@@ -0,0 +1,211 @@
+//! Background health checks of cruncher runners
+//!
+
+use std::time::{Duration, Instant};
+use ya_client_model::activity::activity_state::{ActivityState, State, StatePair};
+use ya_transfer::transfer::Shutdown;
+
+use crate::requests::check_health;
+use crate::{send_state, ExeUnitContext};
+
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    pub interval: Duration,
+    /// Consecutive failed checks after which runners are reported unhealthy
+    pub max_failures: u32,
+    /// Time after which unhealthy activity is terminated
+    pub terminate_after: Option<Duration>,
+}
+
+/// Action following a health check.
+#[derive(Debug, Clone, PartialEq)]
+enum HealthAction {
+    None,
+    ReportUnhealthy(String),
+    ReportHealthy,
+    Terminate,
+}
+
+/// Counts consecutive failed health checks.
+#[derive(Debug)]
+struct HealthTracker {
+    max_failures: u32,
+    terminate_after: Option<Duration>,
+    failures: u32,
+    unhealthy_since: Option<Instant>,
+}
+
+impl HealthTracker {
+    fn new(config: &WatchdogConfig) -> Self {
+        Self {
+            max_failures: config.max_failures.max(1),
+            terminate_after: config.terminate_after,
+            failures: 0,
+            unhealthy_since: None,
+        }
+    }
+
+    fn record(&mut self, check: Result<(), String>, now: Instant) -> HealthAction {
+        match check {
+            Ok(()) => {
+                self.failures = 0;
+                if self.unhealthy_since.take().is_some() {
+                    log::info!("Runners healthy again");
+                    return HealthAction::ReportHealthy;
+                }
+                HealthAction::None
+            }
+            Err(e) => {
+                self.failures = self.failures.saturating_add(1);
+                log::warn!("Runners health check failed ({}): {e}", self.failures);
+                let became_unhealthy = self.failures == self.max_failures;
+                if became_unhealthy {
+                    log::error!("Runners unhealthy after {} failed checks", self.failures);
+                    self.unhealthy_since = Some(now);
+                }
+                match (self.unhealthy_since, self.terminate_after) {
+                    (Some(since), Some(terminate_after))
+                        if now.saturating_duration_since(since) >= terminate_after =>
+                    {
+                        log::error!(
+                            "Runners unhealthy for {}s. Terminating activity",
+                            now.saturating_duration_since(since).as_secs()
+                        );
+                        HealthAction::Terminate
+                    }
+                    _ if became_unhealthy => {
+                        HealthAction::ReportUnhealthy(format!("Runners unhealthy: {e}"))
+                    }
+                    _ => HealthAction::None,
+                }
+            }
+        }
+    }
+}
+
+/// Polls client API health endpoint once activity is deployed. Unhealthy runners
+/// are reported with `ActivityState::error_message`, cleared again on recovery.
+pub fn spawn_health_watchdog(ctx: ExeUnitContext, config: WatchdogConfig) {
+    log::info!(
+        "Checking runners health every {}s",
+        config.interval.as_secs()
+    );
+    tokio::task::spawn_local(async move {
+        let mut tracker = HealthTracker::new(&config);
+        loop {
+            tokio::time::sleep(config.interval).await;
+            match ctx.state.borrow().state.0 {
+                State::Terminated => break,
+                State::Deployed | State::Ready => (),
+                _ => continue,
+            }
+
+            let check = check_health().await.map_err(|e| e.to_string());
+            // Activity state could change while the check was running
+            let state = ctx.state.borrow().state;
+            if state.0 == State::Terminated {
+                break;
+            }
+            match tracker.record(check, Instant::now()) {
+                HealthAction::None => (),
+                HealthAction::ReportUnhealthy(message) => report(&ctx, state, Some(message)).await,
+                HealthAction::ReportHealthy => report(&ctx, state, None).await,
+                HealthAction::Terminate => {
+                    ctx.transfers.send(Shutdown {}).await.ok();
+                    report(
+                        &ctx,
+                        StatePair(State::Terminated, None),
+                        Some("Runners unhealthy".to_string()),
+                    )
+                    .await;
+                    break;
+                }
+            }
+        }
+    });
+}
+
+async fn report(ctx: &ExeUnitContext, state: StatePair, error_message: Option<String>) {
+    let state = ActivityState {
+        state,
+        reason: None,
+        error_message,
+    };
+    if let Err(e) = send_state(ctx, state).await {
+        log::error!("Failed to report runners health: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health_tracker(max_failures: u32, terminate_after: Option<Duration>) -> HealthTracker {
+        HealthTracker::new(&WatchdogConfig {
+            interval: Duration::from_secs(10),
+            max_failures,
+            terminate_after,
+        })
+    }
+
+    fn failed() -> Result<(), String> {
+        Err("503".to_string())
+    }
+
+    #[test]
+    fn test_report_after_consecutive_failures() {
+        let mut tracker = health_tracker(3, None);
+        let now = Instant::now();
+        assert_eq!(tracker.record(failed(), now), HealthAction::None);
+        assert_eq!(tracker.record(failed(), now), HealthAction::None);
+        // Success resets the counter
+        assert_eq!(tracker.record(Ok(()), now), HealthAction::None);
+        assert_eq!(tracker.record(failed(), now), HealthAction::None);
+        assert_eq!(tracker.record(failed(), now), HealthAction::None);
+        assert_eq!(
+            tracker.record(failed(), now),
+            HealthAction::ReportUnhealthy("Runners unhealthy: 503".to_string())
+        );
+        // Reported only once, and never terminated without grace period
+        assert_eq!(
+            tracker.record(failed(), now + Duration::from_secs(3600)),
+            HealthAction::None
+        );
+        assert_eq!(tracker.record(Ok(()), now), HealthAction::ReportHealthy);
+        assert_eq!(tracker.record(Ok(()), now), HealthAction::None);
+    }
+
+    #[test]
+    fn test_terminate_after_grace_period() {
+        let grace = Duration::from_secs(60);
+        let mut tracker = health_tracker(1, Some(grace));
+        let now = Instant::now();
+        assert!(matches!(
+            tracker.record(failed(), now),
+            HealthAction::ReportUnhealthy(_)
+        ));
+        assert_eq!(
+            tracker.record(failed(), now + grace / 2),
+            HealthAction::None
+        );
+        assert_eq!(
+            tracker.record(failed(), now + grace),
+            HealthAction::Terminate
+        );
+
+        // Recovery within grace period cancels termination
+        let mut tracker = health_tracker(1, Some(grace));
+        assert!(matches!(
+            tracker.record(failed(), now),
+            HealthAction::ReportUnhealthy(_)
+        ));
+        assert_eq!(
+            tracker.record(Ok(()), now + grace / 2),
+            HealthAction::ReportHealthy
+        );
+        assert_eq!(
+            tracker.record(failed(), now + grace),
+            HealthAction::ReportUnhealthy("Runners unhealthy: 503".to_string())
+        );
+    }
+}
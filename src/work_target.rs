@@ -0,0 +1,323 @@
+//! Parsing and validation of work targets
+//!
+
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use sha3::{Digest, Keccak256};
+use std::fmt;
+
+use crate::error::CruncherError;
+use crate::requests::{send_work_target, start_work, stop_work, RunnersResponse, WorkTarget};
+use crate::session::update_session;
+use crate::ExeUnitContext;
+
+/// Parses positional work target arguments, e.g. `factory 0x...`.
+pub fn parse_work_target(args: &[String]) -> Result<WorkTarget, CruncherError> {
+    let first_arg = args
+        .first()
+        .ok_or_else(|| CruncherError::InvalidArgument("Missing work target arg".to_string()))?;
+
+    if first_arg == "factory" {
+        let sec_arg = args
+            .get(1)
+            .ok_or_else(|| CruncherError::InvalidArgument("Missing factory arg".to_string()))?;
+        Ok(WorkTarget::Factory(sec_arg.to_string()))
+    } else if first_arg == "public_key_base" {
+        let sec_arg = args.get(1).ok_or_else(|| {
+            CruncherError::InvalidArgument("Missing public key base arg".to_string())
+        })?;
+        Ok(WorkTarget::PublicKeyBase(sec_arg.to_string()))
+    } else if first_arg == "create2" {
+        let deployer = args
+            .get(1)
+            .ok_or_else(|| CruncherError::InvalidArgument("Missing deployer arg".to_string()))?;
+        let init_code_hash = args.get(2).ok_or_else(|| {
+            CruncherError::InvalidArgument("Missing init code hash arg".to_string())
+        })?;
+        Ok(WorkTarget::Create2 {
+            deployer: deployer.to_string(),
+            init_code_hash: init_code_hash.to_string(),
+            salt_prefix: args.get(3).cloned(),
+        })
+    } else if first_arg == "default" {
+        Ok(WorkTarget::Default)
+    } else {
+        Err(CruncherError::InvalidArgument(
+            "Unknown work target".to_string(),
+        ))
+    }
+}
+
+/// Checks sanitized work target against provider policy, sends it to client API
+/// and persists it in the session.
+pub async fn apply_work_target(
+    ctx: &ExeUnitContext,
+    work_target: WorkTarget,
+) -> Result<(), CruncherError> {
+    ctx.runtime_config.policy.check_work_target(&work_target)?;
+    let _lock = ctx.work_target_lock.lock().await;
+    let pattern = ctx.session.borrow().pattern.clone();
+    send_work_target(work_target.clone(), pattern.as_ref()).await?;
+    update_session(ctx, |session| session.work_target = Some(work_target))
+}
+
+/// Starts runners and persists started state in the session.
+pub async fn start_runners(ctx: &ExeUnitContext) -> Result<RunnersResponse, CruncherError> {
+    let work_target = ctx.session.borrow().work_target.clone();
+    ctx.runtime_config
+        .policy
+        .check_runners_target(work_target.as_ref())?;
+    let response = start_work().await?;
+    update_session(ctx, |session| session.started = true)?;
+    Ok(response)
+}
+
+/// Stops runners and persists stopped state in the session.
+pub async fn stop_runners(ctx: &ExeUnitContext) -> Result<RunnersResponse, CruncherError> {
+    let response = stop_work().await?;
+    update_session(ctx, |session| session.started = false)?;
+    Ok(response)
+}
+
+/// Validates work target and normalizes its encoding.
+pub fn sanitize_work_target(work_target: WorkTarget) -> Result<WorkTarget, CruncherError> {
+    Ok(match work_target {
+        WorkTarget::Factory(f) => {
+            let address = parse_address(&f, "factory")?;
+            WorkTarget::Factory(format!("0x{}", hex::encode(address)))
+        }
+        WorkTarget::PublicKeyBase(p) => {
+            let public_key_base = parse_public_key(&p)?;
+            WorkTarget::PublicKeyBase(format!("0x{}", hex::encode(public_key_base)))
+        }
+        WorkTarget::Create2 {
+            deployer,
+            init_code_hash,
+            salt_prefix,
+        } => {
+            let deployer = parse_address(&deployer, "deployer")?;
+            let init_code_hash = parse_hex(&init_code_hash, "init code hash")?;
+            if init_code_hash.len() != 32 {
+                return Err(CruncherError::InvalidArgument(
+                    "Wrong init code hash data len".to_string(),
+                ));
+            }
+            let salt_prefix = salt_prefix
+                .map(|salt_prefix| parse_hex(&salt_prefix, "salt prefix"))
+                .transpose()?
+                .filter(|salt_prefix| !salt_prefix.is_empty());
+            // At least one byte of the salt has to be left for crunching
+            if salt_prefix
+                .as_ref()
+                .is_some_and(|prefix| prefix.len() >= 32)
+            {
+                return Err(CruncherError::InvalidArgument(
+                    "Wrong salt prefix data len".to_string(),
+                ));
+            }
+            WorkTarget::Create2 {
+                deployer: format!("0x{}", hex::encode(deployer)),
+                init_code_hash: format!("0x{}", hex::encode(init_code_hash)),
+                salt_prefix: salt_prefix
+                    .map(|salt_prefix| format!("0x{}", hex::encode(salt_prefix))),
+            }
+        }
+        WorkTarget::Default => WorkTarget::Default,
+    })
+}
+
+fn parse_hex(value: &str, name: &str) -> Result<Vec<u8>, CruncherError> {
+    decode_hex(value.strip_prefix("0x").unwrap_or(value), name)
+}
+
+/// Decodes hex digits given without `0x` prefix.
+fn decode_hex(digits: &str, name: &str) -> Result<Vec<u8>, CruncherError> {
+    hex::decode(digits).map_err(|_| CruncherError::InvalidArgument(format!("Invalid {name}")))
+}
+
+/// Decodes 20 bytes address. Mixed-case input has to be a valid EIP-55 checksum.
+pub(crate) fn parse_address(address: &str, name: &str) -> Result<[u8; 20], CruncherError> {
+    let hex_address = address.strip_prefix("0x").unwrap_or(address);
+    let address: [u8; 20] = decode_hex(hex_address, &format!("{name} address"))?
+        .try_into()
+        .map_err(|_| CruncherError::InvalidArgument(format!("Wrong {name} data len")))?;
+
+    let is_mixed_case = hex_address.chars().any(|c| c.is_ascii_uppercase())
+        && hex_address.chars().any(|c| c.is_ascii_lowercase());
+    if is_mixed_case && to_checksum_address(&address)[2..] != *hex_address {
+        return Err(CruncherError::InvalidArgument(format!(
+            "Invalid EIP-55 checksum of {name} address 0x{hex_address}"
+        )));
+    }
+    Ok(address)
+}
+
+/// Decodes secp256k1 public key given as 64 raw bytes, 65 bytes with `0x04` prefix
+/// or 33 bytes compressed. Returns 64 bytes of uncompressed point without prefix.
+pub(crate) fn parse_public_key(public_key: &str) -> Result<[u8; 64], CruncherError> {
+    let bytes = hex::decode(public_key.strip_prefix("0x").unwrap_or(public_key))
+        .map_err(|_| CruncherError::InvalidArgument("Invalid public key base".to_string()))?;
+    let sec1_bytes = match bytes.len() {
+        64 => [&[0x04], bytes.as_slice()].concat(),
+        33 | 65 => bytes,
+        _ => {
+            return Err(CruncherError::InvalidArgument(
+                "Wrong public key base data len".to_string(),
+            ))
+        }
+    };
+    let public_key = k256::PublicKey::from_sec1_bytes(&sec1_bytes).map_err(|_| {
+        CruncherError::InvalidArgument("Public key base is not a valid secp256k1 point".to_string())
+    })?;
+    let mut uncompressed = [0u8; 64];
+    uncompressed.copy_from_slice(&public_key.to_encoded_point(false).as_bytes()[1..]);
+    Ok(uncompressed)
+}
+
+/// Encodes address with EIP-55 checksum.
+pub fn to_checksum_address(address: &[u8; 20]) -> String {
+    let hex_address = hex::encode(address);
+    let hash = Keccak256::digest(hex_address.as_bytes());
+    let checksummed: String = hex_address
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+    format!("0x{checksummed}")
+}
+
+/// Factory addresses are displayed with EIP-55 checksum.
+impl fmt::Display for WorkTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkTarget::Factory(factory) => match parse_address(factory, "factory") {
+                Ok(address) => write!(f, "factory {}", to_checksum_address(&address)),
+                Err(_) => write!(f, "factory {factory}"),
+            },
+            WorkTarget::PublicKeyBase(public_key_base) => {
+                write!(f, "public key base {public_key_base}")
+            }
+            WorkTarget::Create2 {
+                deployer,
+                init_code_hash,
+                salt_prefix,
+            } => {
+                match parse_address(deployer, "deployer") {
+                    Ok(address) => write!(f, "create2 deployer {}", to_checksum_address(&address))?,
+                    Err(_) => write!(f, "create2 deployer {deployer}")?,
+                }
+                write!(f, " init code hash {init_code_hash}")?;
+                match salt_prefix {
+                    Some(salt_prefix) => write!(f, " salt prefix {salt_prefix}"),
+                    None => Ok(()),
+                }
+            }
+            WorkTarget::Default => write!(f, "default"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vectors from EIP-55
+    const CHECKSUMMED: [&str; 4] = [
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn test_checksum_address() {
+        for checksummed in CHECKSUMMED {
+            let address = parse_address(checksummed, "factory").unwrap();
+            assert_eq!(to_checksum_address(&address), checksummed);
+            assert_eq!(
+                parse_address(&checksummed.to_lowercase(), "factory").unwrap(),
+                address
+            );
+            assert_eq!(
+                parse_address(&checksummed[2..].to_uppercase(), "factory").unwrap(),
+                address
+            );
+        }
+    }
+
+    #[test]
+    fn test_reject_double_hex_prefix() {
+        let address = CHECKSUMMED[0].to_lowercase();
+        assert!(parse_address(&format!("0x{address}"), "factory").is_err());
+        assert!(parse_hex(&format!("0x{address}"), "salt prefix").is_err());
+        assert!(sanitize_work_target(WorkTarget::Factory(format!("0x{address}"))).is_err());
+    }
+
+    // secp256k1 generator point
+    const G_X: &str = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    const G_Y: &str = "483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+
+    #[test]
+    fn test_public_key_forms() {
+        let uncompressed = format!("0x{G_X}{G_Y}");
+        for public_key in [
+            format!("{G_X}{G_Y}"),
+            format!("0x04{G_X}{G_Y}"),
+            format!("0x02{G_X}"),
+        ] {
+            let sanitized = sanitize_work_target(WorkTarget::PublicKeyBase(public_key)).unwrap();
+            assert!(matches!(sanitized, WorkTarget::PublicKeyBase(p) if p == uncompressed));
+        }
+    }
+
+    #[test]
+    fn test_reject_off_curve_public_key() {
+        let off_curve = format!("{G_X}{}", "00".repeat(32));
+        assert!(matches!(
+            sanitize_work_target(WorkTarget::PublicKeyBase(off_curve)),
+            Err(CruncherError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_reject_invalid_checksum() {
+        let typo = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD";
+        assert!(matches!(
+            sanitize_work_target(WorkTarget::Factory(typo.to_string())),
+            Err(CruncherError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_create2_target() {
+        let args: Vec<String> = ["create2", CHECKSUMMED[0], &format!("0x{G_X}"), "0x01"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let target = sanitize_work_target(parse_work_target(&args).unwrap()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&target).unwrap(),
+            serde_json::json!({
+                "create2": {
+                    "deployer": CHECKSUMMED[0].to_lowercase(),
+                    "initCodeHash": format!("0x{G_X}"),
+                    "saltPrefix": "0x01",
+                }
+            })
+        );
+
+        let long_salt = format!("0x{}", "00".repeat(32));
+        let target = WorkTarget::Create2 {
+            deployer: CHECKSUMMED[0].to_string(),
+            init_code_hash: format!("0x{G_X}"),
+            salt_prefix: Some(long_salt),
+        };
+        assert!(sanitize_work_target(target).is_err());
+    }
+}